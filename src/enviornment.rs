@@ -1,39 +1,242 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::rc::Weak;
 
 use crate::object::ChaiObject;
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Enviornment {
+#[derive(Default)]
+struct Scope {
     state: HashMap<String, ChaiObject>,
-    outer: Option<Box<Enviornment>>,
+    constants: HashSet<String>,
+    outer: Option<Enviornment>,
+}
+
+// A handle to a scope. Clones share the same scope, so closures see (and
+// update) the variables of the scope they were defined in.
+#[derive(Clone, Default)]
+pub struct Enviornment(Rc<RefCell<Scope>>);
+
+#[derive(Clone)]
+pub struct WeakEnviornment(Weak<RefCell<Scope>>);
+
+impl WeakEnviornment {
+    pub fn upgrade(&self) -> Option<Enviornment> {
+        self.0.upgrade().map(Enviornment)
+    }
+}
+
+// How a function refers to the scope it was defined in. A function stored
+// in that same scope, directly or inside an array, hash, record or variant,
+// only holds it weakly, otherwise the two would keep each other alive
+// forever.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Captured {
+    Strong(Enviornment),
+    Weak(WeakEnviornment),
+}
+
+impl Captured {
+    pub fn scope(&self) -> Option<Enviornment> {
+        match self {
+            Captured::Strong(env) => Some(env.clone()),
+            Captured::Weak(env) => env.upgrade(),
+        }
+    }
 }
 
 impl Enviornment {
     pub fn new() -> Self {
-        Enviornment {
-            state: HashMap::new(),
-            outer: None,
-        }
+        Enviornment::default()
     }
 
     pub fn enclosed(outer: Enviornment) -> Self {
-        Enviornment {
-            state: HashMap::new(),
-            outer: Some(outer.into()),
+        Enviornment(Rc::new(RefCell::new(Scope {
+            outer: Some(outer),
+            ..Scope::default()
+        })))
+    }
+
+    pub fn capture(&self) -> Captured {
+        Captured::Strong(self.clone())
+    }
+
+    pub fn downgrade(&self) -> WeakEnviornment {
+        WeakEnviornment(Rc::downgrade(&self.0))
+    }
+
+    // Values go into a scope holding it weakly, and anything else strongly
+    // since it may be stored nowhere else
+    fn stored(&self, value: &mut ChaiObject) {
+        let weak = self.downgrade();
+        recapture(value, &|captured| match captured {
+            Captured::Strong(env) if env == self => Some(Captured::Weak(self.downgrade())),
+            Captured::Weak(env) if *env != weak => env.upgrade().map(Captured::Strong),
+            _ => None,
+        });
+    }
+
+    // Called on values that outlive the scope they were read from, like the
+    // result of a block or a function call
+    pub fn escape(value: &mut ChaiObject) {
+        recapture(value, &|captured| match captured {
+            Captured::Weak(env) => env.upgrade().map(Captured::Strong),
+            Captured::Strong(_) => None,
+        });
+    }
+
+    fn outer(&self) -> Option<Enviornment> {
+        self.0.borrow().outer.clone()
+    }
+
+    pub fn set(&mut self, name: &str, mut object: ChaiObject) {
+        self.stored(&mut object);
+        self.0.borrow_mut().state.insert(name.into(), object);
+    }
+
+    // Updates `name` in the scope it was declared in, returns false when it
+    // isn't declared at all
+    pub fn assign(&mut self, name: &str, object: ChaiObject) -> bool {
+        self.update(name, object, |value, object| *value = object)
+            .is_some()
+    }
+
+    // Runs `f` on the value bound to `name` without copying it out of its
+    // scope. `f` must not touch the environment.
+    pub fn with<R>(&self, name: &str, f: impl FnOnce(&ChaiObject) -> R) -> Option<R> {
+        let scope = self.0.borrow();
+        match scope.state.get(name) {
+            Some(value) => Some(f(value)),
+            None => scope.outer.as_ref()?.with(name, f),
         }
     }
 
-    pub fn set(&mut self, name: &str, object: ChaiObject) {
-        self.state.insert(name.into(), object);
+    // Runs `f` on the value bound to `name` and `object`, the value being
+    // stored into it
+    pub fn update<R>(
+        &mut self,
+        name: &str,
+        mut object: ChaiObject,
+        f: impl FnOnce(&mut ChaiObject, ChaiObject) -> R,
+    ) -> Option<R> {
+        let mut scope = self.0.borrow_mut();
+        if let Some(value) = scope.state.get_mut(name) {
+            self.stored(&mut object);
+            return Some(f(value, object));
+        }
+        let outer = scope.outer.clone();
+        drop(scope);
+        outer?.update(name, object, f)
+    }
+
+    pub fn enter_scope(&mut self) {
+        *self = Enviornment::enclosed(self.clone());
+    }
+
+    pub fn exit_scope(&mut self) {
+        if let Some(outer) = self.outer() {
+            *self = outer;
+        }
     }
 
-    pub fn get(&mut self, name: &str) -> Option<ChaiObject> {
-        let value = self.state.get(name.into()).cloned();
-        if !value.is_some() && !self.outer.is_none() {
-            if let Some(outer) = self.outer.clone() {
-                return outer.clone().get(name);
+    pub fn set_constant(&mut self, name: &str) {
+        self.0.borrow_mut().constants.insert(name.into());
+    }
+
+    // Whether `name` is a constant declared in this scope
+    pub fn is_local_constant(&self, name: &str) -> bool {
+        self.0.borrow().constants.contains(name)
+    }
+
+    // Whether the binding `name` resolves to is a constant
+    pub fn is_constant(&self, name: &str) -> bool {
+        let scope = self.0.borrow();
+        if scope.state.contains_key(name) {
+            return scope.constants.contains(name);
+        }
+        match &scope.outer {
+            Some(outer) => outer.is_constant(name),
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<ChaiObject> {
+        self.with(name, ChaiObject::clone)
+    }
+}
+
+// Replaces the captures `f` returns a new value for, in every function in
+// `value`. Shared arrays and hashes are only copied when they hold one.
+fn recapture(value: &mut ChaiObject, f: &impl Fn(&Captured) -> Option<Captured>) {
+    if !recaptures(value, f) {
+        return;
+    }
+    match value {
+        ChaiObject::Function(function) => {
+            if let Some(captured) = f(&function.env) {
+                function.env = captured;
+            }
+        }
+        ChaiObject::Return(value) => recapture(value, f),
+        ChaiObject::Array(array) => {
+            for item in Rc::make_mut(array) {
+                recapture(item, f);
+            }
+        }
+        ChaiObject::Hash(hash) => {
+            for item in Rc::make_mut(hash).values_mut() {
+                recapture(item, f);
+            }
+        }
+        ChaiObject::Record(record) => {
+            for (_, item) in Rc::make_mut(&mut record.fields) {
+                recapture(item, f);
             }
         }
-        value
+        ChaiObject::Variant(variant) => {
+            for item in Rc::make_mut(&mut variant.values) {
+                recapture(item, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn recaptures(value: &ChaiObject, f: &impl Fn(&Captured) -> Option<Captured>) -> bool {
+    match value {
+        ChaiObject::Function(function) => f(&function.env).is_some(),
+        ChaiObject::Return(value) => recaptures(value, f),
+        ChaiObject::Array(array) => array.iter().any(|item| recaptures(item, f)),
+        ChaiObject::Hash(hash) => hash.values().any(|item| recaptures(item, f)),
+        ChaiObject::Record(record) => record.fields.iter().any(|(_, item)| recaptures(item, f)),
+        ChaiObject::Variant(variant) => variant.values.iter().any(|item| recaptures(item, f)),
+        _ => false,
+    }
+}
+
+// Scopes can hold closures that refer back to them, so neither of these
+// look inside the scope.
+impl std::fmt::Debug for Enviornment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Enviornment").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Enviornment {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for WeakEnviornment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeakEnviornment").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for WeakEnviornment {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
     }
 }
@@ -1,5 +1,7 @@
 use std::fmt::Display;
+use std::rc::Rc;
 
+use crate::pattern::Pattern;
 use crate::statement::Statement;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -10,13 +12,37 @@ pub enum Expression {
     Integer(i32),
     Prefix(Prefix, Box<Expression>),
     Infix(Box<Expression>, Operator, Box<Expression>),
+    // `a += b`, kept apart from `a = a + b` so the target is only evaluated once
+    CompoundAssign(Box<Expression>, Operator, Box<Expression>),
     Boolean(bool),
     If(Box<Expression>, Statement, Option<Statement>),
     While(Box<Expression>, Statement),
-    Function(Vec<String>, Statement),
+    For(Option<String>, String, Box<Expression>, Statement),
+    Match(Box<Expression>, Vec<MatchArm>),
+    Function(Vec<Parameter>, Rc<Statement>),
     FunctionCall(Box<Expression>, Vec<Expression>),
+    Member(Box<Expression>, String),
+    StructLiteral(String, Vec<(String, Expression)>),
+    NamedArgument(String, Box<Expression>),
+    Spread(Box<Expression>),
     Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>),
     ArrayIndex(Box<Expression>, Box<Expression>),
+    Range(Option<Box<Expression>>, Option<Box<Expression>>, bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub pattern: Pattern,
+    pub default: Option<Expression>,
+    pub rest: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Statement,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,6 +58,7 @@ pub enum Operator {
     Minus,
     Multiply,
     Divide,
+    Modulo,
     Greaterthan,
     Lessthan,
     Equals,
@@ -46,6 +73,7 @@ impl Display for Operator {
             Operator::Minus => operator_string.push('-'),
             Operator::Multiply => operator_string.push('x'),
             Operator::Divide => operator_string.push('x'),
+            Operator::Modulo => operator_string.push('%'),
             Operator::Greaterthan => operator_string.push('>'),
             Operator::Lessthan => operator_string.push('<'),
             Operator::Equals => operator_string.push_str("=="),
@@ -1,11 +1,26 @@
 use crate::enviornment::Enviornment;
 use crate::expression::Expression;
+use crate::expression::MatchArm;
 use crate::expression::Operator;
+use crate::expression::Parameter;
 use crate::expression::Prefix;
 use crate::object::ChaiObject;
+use crate::object::EnumType;
 use crate::object::Function;
+use crate::object::HashKey;
+use crate::object::Module;
+use crate::object::Record;
+use crate::object::StructType;
+use crate::object::Variant;
+use crate::object::VariantType;
+use crate::parser::Parser;
+use crate::pattern::Pattern;
+use crate::sourcemap::FileId;
+use crate::statement::ImportNames;
 use crate::statement::Statement;
 use crate::stdchai::Std;
+use std::collections::BTreeMap;
+use std::rc::Rc;
 
 const TRUE: ChaiObject = ChaiObject::Boolean(true);
 const FALSE: ChaiObject = ChaiObject::Boolean(false);
@@ -26,13 +41,7 @@ fn is_error(object: &ChaiObject) -> bool {
 
 fn is_truthy(object: ChaiObject) -> bool {
     match object {
-        ChaiObject::Boolean(boolean) => {
-            if boolean {
-                true
-            } else {
-                false
-            }
-        }
+        ChaiObject::Boolean(boolean) => boolean,
         ChaiObject::Null => false,
         _ => true,
     }
@@ -55,14 +64,16 @@ fn eval_bang_operator_expression(object: ChaiObject) -> ChaiObject {
 fn eval_minus_operator_expression(object: ChaiObject) -> ChaiObject {
     match object {
         ChaiObject::Integer(integer) => ChaiObject::Integer(-integer),
-        _ => error(&format!("Unknown operation : -{}", object)),
+        _ => error(&format!("Unknown operation : -{}", object.type_name())),
     }
 }
 
 fn eval_integer_infix_expression(left: i32, operator: Operator, right: i32) -> ChaiObject {
     match operator {
         Operator::Multiply => ChaiObject::Integer(left * right),
+        Operator::Divide | Operator::Modulo if right == 0 => error("Division by zero"),
         Operator::Divide => ChaiObject::Integer(left / right),
+        Operator::Modulo => ChaiObject::Integer(left % right),
         Operator::Plus => ChaiObject::Integer(left + right),
         Operator::Minus => ChaiObject::Integer(left - right),
         Operator::Lessthan => ChaiObject::Boolean(left < right),
@@ -90,6 +101,23 @@ fn eval_string_infix_expression(left: String, operator: Operator, right: String)
     }
 }
 
+// Arrays, hashes and records are equal when all of their contents are
+fn eval_structural_infix_expression(
+    left: ChaiObject,
+    operator: Operator,
+    right: ChaiObject,
+) -> ChaiObject {
+    match operator {
+        Operator::Equals => ChaiObject::Boolean(left == right),
+        Operator::Notequals => ChaiObject::Boolean(left != right),
+        _ => error(&format!(
+            "Unknown operator for {} : {}",
+            left.type_name(),
+            operator
+        )),
+    }
+}
+
 fn eval_prefix_expression(prefix: Prefix, object: ChaiObject) -> ChaiObject {
     match prefix {
         Prefix::Minus => eval_minus_operator_expression(object),
@@ -108,34 +136,618 @@ fn eval_infix_expression(left: ChaiObject, operator: Operator, right: ChaiObject
         (ChaiObject::String(left), ChaiObject::String(right)) => {
             eval_string_infix_expression(left, operator, right)
         }
-        _ => error(&format!("Type mismatch : {} {} {}", left, operator, right)),
+        (ChaiObject::Array(_), ChaiObject::Array(_))
+        | (ChaiObject::Hash(_), ChaiObject::Hash(_))
+        | (ChaiObject::Record(_), ChaiObject::Record(_)) => {
+            eval_structural_infix_expression(left, operator, right)
+        }
+        (ChaiObject::Variant(a), ChaiObject::Variant(b)) if a.enum_name == b.enum_name => {
+            eval_structural_infix_expression(left, operator, right)
+        }
+        _ => error(&format!(
+            "Type mismatch : {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+    }
+}
+
+// Resolves the bounds of a slice into `0..len`, open ends default to the
+// start and the end of the sliced value.
+fn slice_bounds(start: Option<i32>, end: Option<i32>, len: usize) -> Option<(usize, usize)> {
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(len as i32);
+    if start < 0 || start > end || end as usize > len {
+        return None;
     }
+    Some((start as usize, end as usize))
 }
 
-fn eval_array_index_expression(array: ChaiObject, index: ChaiObject) -> ChaiObject {
+fn eval_array_index_expression(array: &ChaiObject, index: &ChaiObject) -> ChaiObject {
     match (array, index) {
+        (ChaiObject::Array(array), ChaiObject::Range(start, end)) => {
+            match slice_bounds(*start, *end, array.len()) {
+                Some((start, end)) => ChaiObject::Array(array[start..end].to_vec().into()),
+                None => error("Slice out of bound"),
+            }
+        }
+        (ChaiObject::String(string), ChaiObject::Range(start, end)) => {
+            match slice_bounds(*start, *end, string.chars().count()) {
+                Some((start, end)) => {
+                    ChaiObject::String(string.chars().skip(start).take(end - start).collect())
+                }
+                None => error("Slice out of bound"),
+            }
+        }
         (ChaiObject::Array(array), ChaiObject::Integer(index)) => {
-            match array.get(index as usize).cloned() {
+            match array.get(*index as usize).cloned() {
                 Some(object) => object,
                 None => error("Array out of bound"),
             }
         }
+        (ChaiObject::Hash(hash), key) => match HashKey::from_object(key) {
+            Some(hash_key) => match hash.get(&hash_key).cloned() {
+                Some(object) => object,
+                None => error(&format!("Key not found : {}", key)),
+            },
+            None => error(&format!("Unusable as hash key : {}", key.type_name())),
+        },
         _ => error("Index operator not supported"),
     }
 }
 
+fn eval_range_bound(
+    bound: Option<Box<Expression>>,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> Result<Option<i32>, ChaiObject> {
+    match bound {
+        Some(bound) => match eval_expression(*bound, env, std) {
+            ChaiObject::Integer(integer) => Ok(Some(integer)),
+            ChaiObject::Error(err) => Err(ChaiObject::Error(err)),
+            object => Err(error(&format!(
+                "Range bounds should be Integer, found {}",
+                object.type_name()
+            ))),
+        },
+        None => Ok(None),
+    }
+}
+
+fn eval_range_expression(
+    start: Option<Box<Expression>>,
+    end: Option<Box<Expression>>,
+    inclusive: bool,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    let start = match eval_range_bound(start, env, std) {
+        Ok(start) => start,
+        Err(err) => return err,
+    };
+    let mut end = match eval_range_bound(end, env, std) {
+        Ok(end) => end,
+        Err(err) => return err,
+    };
+
+    // Ranges are stored half open, `a..=b` is `a..b + 1`
+    if inclusive {
+        end = match end.and_then(|end| end.checked_add(1)) {
+            Some(end) => Some(end),
+            None => return error("Range end out of bound"),
+        };
+    }
+    ChaiObject::Range(start, end)
+}
+
+fn eval_hash_literal(
+    pairs: Vec<(Expression, Expression)>,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    let mut hash = BTreeMap::new();
+    for (key, value) in pairs {
+        let key = eval_expression(key, env, std);
+        if is_error(&key) {
+            return key;
+        }
+
+        let hash_key = match HashKey::from_object(&key) {
+            Some(hash_key) => hash_key,
+            None => return error(&format!("Unusable as hash key : {}", key.type_name())),
+        };
+
+        let value = eval_expression(value, env, std);
+        if is_error(&value) {
+            return value;
+        }
+        hash.insert(hash_key, value);
+    }
+    ChaiObject::Hash(hash.into())
+}
+
+fn eval_for_expression(
+    index: Option<String>,
+    item: String,
+    collection: ChaiObject,
+    body: Statement,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    let open = matches!(collection, ChaiObject::Range(Some(_), None));
+    let items: Box<dyn Iterator<Item = ChaiObject>> = match collection {
+        ChaiObject::Array(array) => Box::new((0..array.len()).map(move |i| array[i].clone())),
+        ChaiObject::String(string) => Box::new(
+            string
+                .chars()
+                .map(|ch| ChaiObject::String(ch.to_string()))
+                .collect::<Vec<ChaiObject>>()
+                .into_iter(),
+        ),
+        ChaiObject::Range(Some(start), Some(end)) => {
+            Box::new((start..end).map(ChaiObject::Integer))
+        }
+        // Stops after the largest Integer instead of overflowing
+        ChaiObject::Range(Some(start), None) => Box::new(
+            std::iter::successors(Some(start), |i| i.checked_add(1)).map(ChaiObject::Integer),
+        ),
+        _ => return error(&format!("Cannot iterate over {}", collection.type_name())),
+    };
+
+    let mut result = NULL;
+    let mut exhausted = true;
+    for (i, object) in items.enumerate() {
+        // Loop variables live in a scope around the body, a new one every
+        // iteration so closures keep the values of their own iteration
+        env.enter_scope();
+        if let Some(index) = &index {
+            env.set(index, ChaiObject::Integer(i as i32));
+        }
+        env.set(&item, object);
+
+        let output = eval(body.clone(), env, std);
+        env.exit_scope();
+        match output {
+            ChaiObject::Return(_) | ChaiObject::Error(_) => {
+                result = output;
+                exhausted = false;
+                break;
+            }
+            ChaiObject::Break => {
+                exhausted = false;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    if open && exhausted {
+        result = error(&format!("Range overflowed past {}", i32::MAX));
+    }
+    result
+}
+
+// Whether `name` is a unit variant of the enum `variant` belongs to, a bare
+// `Empty` in a match arm is that variant rather than a new binding
+fn is_unit_variant(name: &str, variant: &Variant, env: &Enviornment) -> bool {
+    env.with(&variant.enum_name, |object| match object {
+        ChaiObject::Enum(enum_type) => enum_type
+            .variants
+            .iter()
+            .any(|other| other.name == name && other.fields.is_empty()),
+        _ => false,
+    })
+    .unwrap_or(false)
+}
+
+fn match_pattern(
+    pattern: &Pattern,
+    value: &ChaiObject,
+    bindings: &mut Vec<(String, ChaiObject)>,
+    env: &Enviornment,
+) -> bool {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Ident(name), ChaiObject::Variant(variant))
+            if is_unit_variant(name, variant, env) =>
+        {
+            *name == variant.name
+        }
+        (Pattern::Ident(name), value) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        (Pattern::Integer(pattern), ChaiObject::Integer(integer)) => pattern == integer,
+        (Pattern::String(pattern), ChaiObject::String(string)) => pattern == string,
+        (Pattern::Boolean(pattern), ChaiObject::Boolean(boolean)) => pattern == boolean,
+        (Pattern::Array(elements, rest), ChaiObject::Array(array)) => {
+            if array.len() < elements.len() || (rest.is_none() && array.len() > elements.len()) {
+                return false;
+            }
+            let elements_match = elements
+                .iter()
+                .zip(array.iter())
+                .all(|(element, value)| match_pattern(element, value, bindings, env));
+            if !elements_match {
+                return false;
+            }
+            if let Some(rest) = rest {
+                let rest_array = array[elements.len()..].to_vec();
+                bindings.push((rest.clone(), ChaiObject::Array(rest_array.into())));
+            }
+            true
+        }
+        (Pattern::Hash(keys), ChaiObject::Record(record)) => {
+            keys.iter().all(|key| match record.get(key) {
+                Some(value) => {
+                    bindings.push((key.clone(), value.clone()));
+                    true
+                }
+                None => false,
+            })
+        }
+        (Pattern::Hash(keys), ChaiObject::Hash(hash)) => {
+            keys.iter()
+                .all(|key| match hash.get(&HashKey::String(key.clone())) {
+                    Some(value) => {
+                        bindings.push((key.clone(), value.clone()));
+                        true
+                    }
+                    None => false,
+                })
+        }
+        (Pattern::Variant(name, values), ChaiObject::Variant(variant)) => {
+            (*name == variant.name || *name == format!("{}.{}", variant.enum_name, variant.name))
+                && values.len() == variant.values.len()
+                && values
+                    .iter()
+                    .zip(variant.values.iter())
+                    .all(|(pattern, value)| match_pattern(pattern, value, bindings, env))
+        }
+        (Pattern::Alternative(patterns), value) => patterns.iter().any(|pattern| {
+            let mut alternative = Vec::new();
+            let matched = match_pattern(pattern, value, &mut alternative, env);
+            if matched {
+                bindings.extend(alternative);
+            }
+            matched
+        }),
+        _ => false,
+    }
+}
+
+fn eval_match_expression(
+    value: ChaiObject,
+    arms: Vec<MatchArm>,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    for arm in arms {
+        let mut bindings = Vec::new();
+        if !match_pattern(&arm.pattern, &value, &mut bindings, env) {
+            continue;
+        }
+
+        // Bindings are only visible to the guard and the body of their arm
+        env.enter_scope();
+        for (name, object) in bindings {
+            env.set(&name, object);
+        }
+
+        let guard = match arm.guard {
+            Some(guard) => eval_expression(guard, env, std),
+            None => TRUE,
+        };
+        if is_error(&guard) {
+            env.exit_scope();
+            return guard;
+        }
+        if !is_truthy(guard) {
+            env.exit_scope();
+            continue;
+        }
+
+        let result = eval(arm.body, env, std);
+        env.exit_scope();
+        return result;
+    }
+    error(&format!("No match arm matches {}", value.type_name()))
+}
+
+// One step into an assignment target, `[index]` or `.field`
+enum Place {
+    Index(ChaiObject),
+    Field(String),
+}
+
+// Splits `grid[i].cells[j]` into the variable it starts from and the
+// evaluated path into it
+fn eval_place(
+    expression: Expression,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> Result<(String, Vec<Place>), ChaiObject> {
+    match expression {
+        Expression::Ident(name) => Ok((name, Vec::new())),
+        Expression::ArrayIndex(container, index) => {
+            let (name, mut path) = eval_place(*container, env, std)?;
+            let index = eval_expression(*index, env, std);
+            if is_error(&index) {
+                return Err(index);
+            }
+            path.push(Place::Index(index));
+            Ok((name, path))
+        }
+        Expression::Member(container, field) => {
+            let (name, mut path) = eval_place(*container, env, std)?;
+            path.push(Place::Field(field));
+            Ok((name, path))
+        }
+        _ => Err(error("Invalid assignment target")),
+    }
+}
+
+// Writes `value` at `path` inside `object` in place, combining it with the
+// current value first for compound assignment. Returns the stored value.
+fn assign_place(
+    object: &mut ChaiObject,
+    path: &[Place],
+    operator: &Option<Operator>,
+    value: ChaiObject,
+) -> ChaiObject {
+    let (place, rest) = match path.split_first() {
+        Some(step) => step,
+        None => {
+            let value = match operator {
+                Some(operator) => eval_infix_expression(object.clone(), operator.clone(), value),
+                None => value,
+            };
+            if !is_error(&value) {
+                *object = value.clone();
+            }
+            return value;
+        }
+    };
+
+    let target = match (object, place) {
+        (ChaiObject::Array(array), Place::Index(ChaiObject::Integer(index))) => {
+            if *index < 0 || *index as usize >= array.len() {
+                return error("Array out of bound");
+            }
+            &mut Rc::make_mut(array)[*index as usize]
+        }
+        (ChaiObject::Hash(hash), place) => {
+            let hash = Rc::make_mut(hash);
+            let key = match place {
+                Place::Index(index) => match HashKey::from_object(index) {
+                    Some(key) => key,
+                    None => return error(&format!("Unusable as hash key : {}", index.type_name())),
+                },
+                Place::Field(field) => HashKey::String(field.clone()),
+            };
+            if rest.is_empty() && operator.is_none() {
+                hash.insert(key, value.clone());
+                return value;
+            }
+            match hash.get_mut(&key) {
+                Some(target) => target,
+                None => return error(&format!("Key not found : {}", ChaiObject::from(key))),
+            }
+        }
+        (ChaiObject::Record(record), Place::Field(field)) => {
+            match Rc::make_mut(&mut record.fields)
+                .iter_mut()
+                .find(|(name, _)| name == field)
+            {
+                Some((_, target)) => target,
+                None => return error(&format!("{} has no field {}", record.name, field)),
+            }
+        }
+        (object, Place::Field(field)) => {
+            return error(&format!("{} has no field {}", object.type_name(), field))
+        }
+        _ => return error("Index assignment not supported"),
+    };
+    assign_place(target, rest, operator, value)
+}
+
+// Matches constructor arguments, positional then named, to the declared
+// fields of a struct or enum variant
+fn bind_fields(
+    name: &str,
+    fields: &[String],
+    arguments: Vec<ChaiObject>,
+    named: Vec<(String, ChaiObject)>,
+) -> Result<Vec<(String, ChaiObject)>, ChaiObject> {
+    if arguments.len() > fields.len() {
+        return Err(error(&format!(
+            "{} expects at most {} fields, found {}",
+            name,
+            fields.len(),
+            arguments.len()
+        )));
+    }
+
+    let mut values: Vec<Option<ChaiObject>> = fields.iter().map(|_| None).collect();
+    for (value, slot) in arguments.into_iter().zip(values.iter_mut()) {
+        *slot = Some(value);
+    }
+    for (field, value) in named {
+        match fields.iter().position(|name| *name == field) {
+            Some(position) if values[position].is_some() => {
+                return Err(error(&format!(
+                    "{} got multiple values for {}",
+                    name, field
+                )))
+            }
+            Some(position) => values[position] = Some(value),
+            None => return Err(error(&format!("{} has no field {}", name, field))),
+        }
+    }
+
+    let mut bound = Vec::new();
+    for (field, value) in fields.iter().zip(values) {
+        match value {
+            Some(value) => bound.push((field.clone(), value)),
+            None => return Err(error(&format!("{} missing field {}", name, field))),
+        }
+    }
+    Ok(bound)
+}
+
+fn construct_record(
+    struct_type: StructType,
+    arguments: Vec<ChaiObject>,
+    named: Vec<(String, ChaiObject)>,
+) -> ChaiObject {
+    match bind_fields(&struct_type.name, &struct_type.fields, arguments, named) {
+        Ok(fields) => ChaiObject::Record(Record {
+            name: struct_type.name,
+            fields: fields.into(),
+        }),
+        Err(err) => err,
+    }
+}
+
+fn construct_variant(
+    variant: VariantType,
+    arguments: Vec<ChaiObject>,
+    named: Vec<(String, ChaiObject)>,
+) -> ChaiObject {
+    let name = format!("{}.{}", variant.enum_name, variant.name);
+    match bind_fields(&name, &variant.fields, arguments, named) {
+        Ok(fields) => ChaiObject::Variant(Variant {
+            enum_name: variant.enum_name,
+            name: variant.name,
+            values: Rc::new(fields.into_iter().map(|(_, value)| value).collect()),
+        }),
+        Err(err) => err,
+    }
+}
+
+// `Shape.Circle` is a constructor, unit variants like `Shape.Empty` are
+// values on their own
+fn eval_enum_variant(enum_type: EnumType, name: &str) -> ChaiObject {
+    match enum_type
+        .variants
+        .into_iter()
+        .find(|variant| variant.name == name)
+    {
+        Some(variant) if variant.fields.is_empty() => ChaiObject::Variant(Variant {
+            enum_name: variant.enum_name,
+            name: variant.name,
+            values: Rc::default(),
+        }),
+        Some(variant) => ChaiObject::VariantConstructor(variant),
+        None => error(&format!("{} has no variant {}", enum_type.name, name)),
+    }
+}
+
+// The target's indices are evaluated once, before the right side
 fn eval_reassign_expression(
     expression: Expression,
-    right: ChaiObject,
+    operator: Option<Operator>,
+    right: Expression,
     env: &mut Enviornment,
+    std: &mut Std,
 ) -> ChaiObject {
-    if let Expression::Ident(variable) = expression {
-        if env.get(&variable).is_none() {
-            return error(&format!("Variable {} not found", variable));
+    let (variable, path) = match eval_place(expression, env, std) {
+        Ok(place) => place,
+        Err(err) => return err,
+    };
+    if env.is_constant(&variable) {
+        return error(&format!("Cannot assign to constant {}", variable));
+    }
+
+    let right = eval_expression(right, env, std);
+    if is_error(&right) {
+        return right;
+    }
+
+    match env.update(&variable, right, |object, right| {
+        assign_place(object, &path, &operator, right)
+    }) {
+        Some(result) => result,
+        None => error(&format!("Variable {} not found", variable)),
+    }
+}
+
+fn lookup(name: &str, env: &mut Enviornment, std: &mut Std) -> Option<ChaiObject> {
+    if let Some(value) = env.get(name) {
+        return Some(value);
+    }
+    std.get(name).map(ChaiObject::BuildinFunction)
+}
+
+fn eval_member_expression(object: ChaiObject, name: &str) -> ChaiObject {
+    match object {
+        ChaiObject::Hash(hash) => match hash.get(&HashKey::String(name.into())) {
+            Some(value) => value.clone(),
+            None => error(&format!("Key not found : {}", name)),
+        },
+        ChaiObject::Record(record) => match record.get(name) {
+            Some(value) => value.clone(),
+            None => error(&format!("{} has no field {}", record.name, name)),
+        },
+        ChaiObject::Enum(enum_type) => eval_enum_variant(enum_type, name),
+        ChaiObject::Module(module) => match module.exports.get(name) {
+            Some(value) => value.clone(),
+            None => error(&format!("Module {} has no export {}", module.path, name)),
+        },
+        _ => error(&format!("{} has no field {}", object.type_name(), name)),
+    }
+}
+
+// Resolves `object.method(...)` to the function to call and the receiver to
+// pass as its first argument. Functions stored in a hash under `method` are
+// called as they are, anything else is looked up like a variable so
+// `x.len()` is the same as `len(x)`.
+fn eval_method_expression(
+    object: Expression,
+    method: &str,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> (ChaiObject, Option<ChaiObject>) {
+    let object = eval_expression(object, env, std);
+    if is_error(&object) {
+        return (object, None);
+    }
+
+    if let ChaiObject::Hash(hash) = &object {
+        if let Some(function) = hash.get(&HashKey::String(method.into())) {
+            return (function.clone(), None);
+        }
+    }
+
+    if let ChaiObject::Record(record) = &object {
+        if let Some(function) = record.get(method) {
+            return (function.clone(), None);
         }
-        env.set(&variable, right.clone());
     }
-    right
+
+    if let ChaiObject::Enum(enum_type) = object {
+        return (eval_enum_variant(enum_type, method), None);
+    }
+
+    if let ChaiObject::Module(module) = object {
+        return (
+            eval_member_expression(ChaiObject::Module(module), method),
+            None,
+        );
+    }
+
+    match lookup(method, env, std) {
+        Some(function) => (function, Some(object)),
+        None => (
+            error(&format!(
+                "No method {} found for {}",
+                method,
+                object.type_name()
+            )),
+            None,
+        ),
+    }
 }
 
 fn eval_expression(expression: Expression, env: &mut Enviornment, std: &mut Std) -> ChaiObject {
@@ -156,13 +768,12 @@ fn eval_expression(expression: Expression, env: &mut Enviornment, std: &mut Std)
             }
             eval_prefix_expression(prefix, object)
         }
+        Expression::CompoundAssign(left, operator, right) => {
+            eval_reassign_expression(*left, Some(operator), *right, env, std)
+        }
         Expression::Infix(left, operator, right) => {
             if Operator::Assign == operator {
-                let right = eval_expression(*right, env, std);
-                if is_error(&right) {
-                    return right;
-                }
-                return eval_reassign_expression(*left, right, env);
+                return eval_reassign_expression(*left, None, *right, env, std);
             }
 
             let left = eval_expression(*left, env, std);
@@ -182,10 +793,8 @@ fn eval_expression(expression: Expression, env: &mut Enviornment, std: &mut Std)
             }
             if is_truthy(condition) {
                 return eval(then, env, std);
-            } else {
-                if otherwise.is_some() {
-                    return eval(otherwise.unwrap(), env, std);
-                }
+            } else if let Some(otherwise) = otherwise {
+                return eval(otherwise, env, std);
             }
             NULL
         }
@@ -201,74 +810,296 @@ fn eval_expression(expression: Expression, env: &mut Enviornment, std: &mut Std)
                     break;
                 }
 
-                eval(body.clone(), env, std);
+                let output = eval(body.clone(), env, std);
+                match output {
+                    ChaiObject::Return(_) | ChaiObject::Error(_) => return output,
+                    ChaiObject::Break => break,
+                    _ => {}
+                }
             }
             NULL
         }
-        Expression::Ident(name) => {
-            let value = env.clone().get(&name).clone();
-
-            if let Some(value) = value {
+        Expression::For(index, item, collection, body) => {
+            let collection = eval_expression(*collection, env, std);
+            if is_error(&collection) {
+                return collection;
+            }
+            eval_for_expression(index, item, collection, body, env, std)
+        }
+        Expression::Match(value, arms) => {
+            let value = eval_expression(*value, env, std);
+            if is_error(&value) {
                 return value;
             }
+            eval_match_expression(value, arms, env, std)
+        }
+        Expression::Ident(name) => match lookup(&name, env, std) {
+            Some(value) => value,
+            None => error(&format!("Variable : {} not found", name)),
+        },
+        Expression::StructLiteral(name, fields) => {
+            let struct_type = match lookup(&name, env, std) {
+                Some(ChaiObject::Struct(struct_type)) => struct_type,
+                Some(object) => return error(&format!("{} is not a struct", object.type_name())),
+                None => return error(&format!("Variable : {} not found", name)),
+            };
 
-            let buildin = std.clone().get(&name).clone();
-
-            if let Some(buildin) = buildin {
-                return ChaiObject::BuildinFunction(buildin);
+            let mut named = Vec::new();
+            for (field, value) in fields {
+                let value = eval_expression(value, env, std);
+                if is_error(&value) {
+                    return value;
+                }
+                named.push((field, value));
             }
-
-            return error(&format!("Variable : {} not found", name));
+            construct_record(struct_type, Vec::new(), named)
         }
-        Expression::Function(parameters, body) => ChaiObject::Function(Function(parameters, body)),
+        Expression::Member(object, name) => {
+            let object = eval_expression(*object, env, std);
+            if is_error(&object) {
+                return object;
+            }
+            eval_member_expression(object, &name)
+        }
+        Expression::Function(parameters, body) => ChaiObject::Function(Function {
+            env: env.capture(),
+            name: None,
+            parameters,
+            body,
+        }),
         Expression::FunctionCall(name, arguments) => {
-            let function = eval_expression(*name, env, std);
+            let (function, receiver) = match *name {
+                Expression::Member(object, method) => {
+                    eval_method_expression(*object, &method, env, std)
+                }
+                name => (eval_expression(name, env, std), None),
+            };
             if is_error(&function) {
                 return function;
             }
 
-            let arguments = eval_expressions(arguments, env, std);
+            let (arguments, named_arguments): (Vec<Expression>, Vec<Expression>) = arguments
+                .into_iter()
+                .partition(|argument| !matches!(argument, Expression::NamedArgument(_, _)));
+
+            let mut arguments = eval_expressions(arguments, env, std);
 
             if arguments.len() == 1 && is_error(&arguments[0]) {
                 return arguments[0].clone();
             }
 
-            return apply_function(function, arguments, env, std);
+            if let Some(receiver) = receiver {
+                arguments.insert(0, receiver);
+            }
+
+            let mut named = Vec::new();
+            for argument in named_arguments {
+                if let Expression::NamedArgument(name, value) = argument {
+                    let value = eval_expression(*value, env, std);
+                    if is_error(&value) {
+                        return value;
+                    }
+                    named.push((name, value));
+                }
+            }
+
+            apply_function(function, arguments, named, std)
         }
         Expression::Array(elements) => {
             let elements = eval_expressions(elements, env, std);
             if elements.len() == 1 && is_error(&elements[0]) {
                 return elements[0].clone();
             }
-            ChaiObject::Array(elements)
+            ChaiObject::Array(elements.into())
+        }
+        Expression::Hash(pairs) => eval_hash_literal(pairs, env, std),
+        Expression::Range(start, end, inclusive) => {
+            eval_range_expression(start, end, inclusive, env, std)
+        }
+        Expression::Spread(_) => error("Spread is only allowed in arrays and calls"),
+        Expression::NamedArgument(name, _) => {
+            error(&format!("Named argument {} outside of a call", name))
         }
         Expression::ArrayIndex(array, index) => {
+            let index = eval_expression(*index, env, std);
+            if is_error(&index) {
+                return index;
+            }
+
+            // Index variables where they live instead of copying the whole
+            // collection out first
+            if let Expression::Ident(name) = array.as_ref() {
+                let result = env.with(name, |array| eval_array_index_expression(array, &index));
+                if let Some(result) = result {
+                    return result;
+                }
+            }
+
             let array = eval_expression(*array, env, std);
             if is_error(&array) {
                 return array;
             }
 
-            let index = eval_expression(*index, env, std);
-            if is_error(&index) {
-                return index;
+            eval_array_index_expression(&array, &index)
+        }
+        _ => panic!("Statement not implemented"),
+    }
+}
+
+fn bind_pattern(pattern: &Pattern, value: ChaiObject, env: &mut Enviornment) -> ChaiObject {
+    match (pattern, value) {
+        (Pattern::Ident(name), value) => {
+            env.set(name, value);
+            NULL
+        }
+        (Pattern::Array(elements, rest), ChaiObject::Array(array)) => {
+            let mut array = Rc::unwrap_or_clone(array);
+            if array.len() < elements.len() || (rest.is_none() && array.len() > elements.len()) {
+                return error(&format!(
+                    "Pattern {} doesn't match an array of {} elements",
+                    pattern,
+                    array.len()
+                ));
             }
 
-            eval_array_index_expression(array, index)
+            let rest_array = array.split_off(elements.len());
+            for (element, value) in elements.iter().zip(array) {
+                let result = bind_pattern(element, value, env);
+                if is_error(&result) {
+                    return result;
+                }
+            }
+            if let Some(rest) = rest {
+                env.set(rest, ChaiObject::Array(rest_array.into()));
+            }
+            NULL
         }
-        _ => panic!("Statement not implemented"),
+        (Pattern::Hash(keys), ChaiObject::Record(record)) => {
+            for key in keys {
+                match record.get(key) {
+                    Some(value) => env.set(key, value.clone()),
+                    None => return error(&format!("Pattern {} : key {} not found", pattern, key)),
+                }
+            }
+            NULL
+        }
+        (Pattern::Hash(keys), ChaiObject::Hash(hash)) => {
+            for key in keys {
+                match hash.get(&HashKey::String(key.clone())) {
+                    Some(value) => env.set(key, value.clone()),
+                    None => return error(&format!("Pattern {} : key {} not found", pattern, key)),
+                }
+            }
+            NULL
+        }
+        (_, value) => {
+            let mut bindings = Vec::new();
+            if !match_pattern(pattern, &value, &mut bindings, env) {
+                return error(&format!(
+                    "Pattern {} doesn't match {}",
+                    pattern,
+                    value.type_name()
+                ));
+            }
+            for (name, object) in bindings {
+                env.set(&name, object);
+            }
+            NULL
+        }
+    }
+}
+
+fn parameter_name(parameter: &Parameter) -> Option<&str> {
+    match &parameter.pattern {
+        Pattern::Ident(name) if !parameter.rest => Some(name),
+        _ => None,
     }
 }
 
 fn create_function_env(
-    function: Function,
+    function: &Function,
     arguments: Vec<ChaiObject>,
-    env: &mut Enviornment,
-) -> Enviornment {
-    let mut env = Enviornment::enclosed(env.clone());
-    for (i, param) in function.0.iter().enumerate() {
-        env.set(param, arguments[i].clone());
+    mut named: Vec<(String, ChaiObject)>,
+    std: &mut Std,
+) -> Result<Enviornment, ChaiObject> {
+    let has_rest = function.parameters.iter().any(|parameter| parameter.rest);
+    if !has_rest && arguments.len() > function.parameters.len() {
+        return Err(error(&format!(
+            "{} expects at most {} arguments, found {}",
+            function,
+            function.parameters.len(),
+            arguments.len()
+        )));
     }
-    env
+
+    for (i, (name, _)) in named.iter().enumerate() {
+        let position = function
+            .parameters
+            .iter()
+            .position(|parameter| parameter_name(parameter) == Some(name.as_str()));
+        let repeated = named[..i].iter().any(|(other, _)| other == name);
+        match position {
+            Some(position) if position < arguments.len() || repeated => {
+                return Err(error(&format!(
+                    "{} got multiple values for {}",
+                    function, name
+                )))
+            }
+            Some(_) => {}
+            None => return Err(error(&format!("{} has no parameter {}", function, name))),
+        }
+    }
+
+    let mut arguments = arguments.into_iter();
+    let mut function_env = match function.env.scope() {
+        Some(scope) => Enviornment::enclosed(scope),
+        None => return Err(error(&format!("{} outlived its scope", function))),
+    };
+    for parameter in &function.parameters {
+        if parameter.rest {
+            let rest = ChaiObject::Array(Rc::new(arguments.by_ref().collect()));
+            bind_pattern(&parameter.pattern, rest, &mut function_env);
+            continue;
+        }
+
+        let named_value = parameter_name(parameter).and_then(|name| {
+            let position = named.iter().position(|(named, _)| named == name)?;
+            Some(named.remove(position).1)
+        });
+
+        // Defaults are evaluated in the function scope so they can refer to
+        // the parameters before them.
+        let value = match (arguments.next(), named_value, &parameter.default) {
+            (Some(value), _, _) | (None, Some(value), _) => value,
+            (None, None, Some(default)) => {
+                let value = eval_expression(default.clone(), &mut function_env, std);
+                if is_error(&value) {
+                    return Err(value);
+                }
+                value
+            }
+            (None, None, None) => {
+                return Err(error(&format!(
+                    "{} missing argument for {}",
+                    function, parameter.pattern
+                )))
+            }
+        };
+
+        let result = bind_pattern(&parameter.pattern, value, &mut function_env);
+        if is_error(&result) {
+            return Err(result);
+        }
+    }
+
+    // Anything not bound by now would be silently dropped
+    if let Some((name, _)) = named.first() {
+        return Err(error(&format!(
+            "{} got an unexpected named argument {}",
+            function, name
+        )));
+    }
+    Ok(function_env)
 }
 
 fn function_return_value(object: ChaiObject) -> ChaiObject {
@@ -281,19 +1112,36 @@ fn function_return_value(object: ChaiObject) -> ChaiObject {
 fn apply_function(
     function: ChaiObject,
     arguments: Vec<ChaiObject>,
-    env: &mut Enviornment,
+    named: Vec<(String, ChaiObject)>,
     std: &mut Std,
 ) -> ChaiObject {
     if let ChaiObject::Function(function) = function {
-        let mut function_env = create_function_env(function.clone(), arguments, env);
-        let output = eval(function.1, &mut function_env, std);
-        return function_return_value(output);
+        let function_env = create_function_env(&function, arguments, named, std);
+        let mut function_env = match function_env {
+            Ok(function_env) => function_env,
+            Err(err) => return err,
+        };
+        let output = eval(Statement::clone(&function.body), &mut function_env, std);
+        let mut output = function_return_value(output);
+        Enviornment::escape(&mut output);
+        return output;
+    }
+
+    if let ChaiObject::Struct(struct_type) = function {
+        return construct_record(struct_type, arguments, named);
+    }
+
+    if let ChaiObject::VariantConstructor(variant) = function {
+        return construct_variant(variant, arguments, named);
     }
 
     if let ChaiObject::BuildinFunction(function) = function {
+        if !named.is_empty() {
+            return error("Buildin functions don't accept named arguments");
+        }
         return function(arguments);
     }
-    error(&format!("Not a function : {:?}", function))
+    error(&format!("Not a function : {}", function.type_name()))
 }
 
 fn eval_expressions(
@@ -303,10 +1151,23 @@ fn eval_expressions(
 ) -> Vec<ChaiObject> {
     let mut args = Vec::new();
     for argument in expressions {
-        args.push(eval_expression(argument, env, std));
-        if is_error(&args[0]) {
-            return args;
+        if let Expression::Spread(expression) = argument {
+            match eval_expression(*expression, env, std) {
+                ChaiObject::Array(array) => args.extend(Rc::unwrap_or_clone(array)),
+                ChaiObject::Range(Some(start), Some(end)) => {
+                    args.extend((start..end).map(ChaiObject::Integer))
+                }
+                ChaiObject::Error(err) => return vec![ChaiObject::Error(err)],
+                object => return vec![error(&format!("Cannot spread {}", object.type_name()))],
+            }
+            continue;
+        }
+
+        let object = eval_expression(argument, env, std);
+        if is_error(&object) {
+            return vec![object];
         }
+        args.push(object);
     }
     args
 }
@@ -329,31 +1190,235 @@ pub fn eval(statement: Statement, env: &mut Enviornment, std: &mut Std) -> ChaiO
             }
             ChaiObject::Return(value.into())
         }
-        Statement::Let(name, expression) => {
-            let value = eval_expression(*expression, env, std);
-            if is_error(&value) {
-                return value;
-            }
-            env.set(&name, value.clone());
-            value
+        Statement::Break => ChaiObject::Break,
+        Statement::Continue => ChaiObject::Continue,
+        Statement::Let(pattern, expression) => {
+            eval_declaration(pattern, *expression, false, env, std)
+        }
+        Statement::Const(pattern, expression) => {
+            eval_declaration(pattern, *expression, true, env, std)
+        }
+        // Already declared when the enclosing block was entered
+        Statement::Function(_, _, _) => NULL,
+        Statement::Struct(name, fields) => {
+            let struct_type = StructType {
+                name: name.clone(),
+                fields,
+            };
+            declare(&name, ChaiObject::Struct(struct_type), env)
+        }
+        Statement::Import(path, names, file) => eval_import(path, names, file, env, std),
+        Statement::Export(statement) => eval(*statement, env, std),
+        Statement::Enum(name, variants) => {
+            let variants = variants
+                .into_iter()
+                .map(|(variant, fields)| VariantType {
+                    enum_name: name.clone(),
+                    name: variant,
+                    fields,
+                })
+                .collect();
+            let enum_type = EnumType {
+                name: name.clone(),
+                variants,
+            };
+            declare(&name, ChaiObject::Enum(enum_type), env)
         }
         Statement::Reassignment(name, expression) => {
+            if env.is_constant(&name) {
+                return error(&format!("Cannot assign to constant {}", name));
+            }
             let value = eval_expression(*expression, env, std);
             if is_error(&value) {
                 return value;
             }
-            env.set(&name, value.clone());
+            if !env.assign(&name, value.clone()) {
+                return error(&format!("Variable {} not found", name));
+            }
             value
         }
     }
 }
 
+// `let` and `const`, constants can't be redeclared in the scope they were
+// declared in
+fn eval_declaration(
+    pattern: Pattern,
+    expression: Expression,
+    constant: bool,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    let names = pattern.names();
+    if let Some(name) = names.iter().find(|name| env.is_local_constant(name)) {
+        return redeclared_constant(name);
+    }
+
+    let value = eval_expression(expression, env, std);
+    if is_error(&value) {
+        return value;
+    }
+    let result = bind_pattern(&pattern, value.clone(), env);
+    if is_error(&result) {
+        return result;
+    }
+
+    if constant {
+        for name in names {
+            env.set_constant(&name);
+        }
+    }
+    value
+}
+
+fn redeclared_constant(name: &str) -> ChaiObject {
+    error(&format!("Cannot redeclare constant {}", name))
+}
+
+// Binds `name` in the current scope for the declaration forms other than
+// `let` and `const`
+fn declare(name: &str, value: ChaiObject, env: &mut Enviornment) -> ChaiObject {
+    if env.is_local_constant(name) {
+        return redeclared_constant(name);
+    }
+    env.set(name, value);
+    NULL
+}
+
+fn declare_function(
+    name: String,
+    parameters: Vec<Parameter>,
+    body: Rc<Statement>,
+    env: &mut Enviornment,
+) -> ChaiObject {
+    let function = Function {
+        env: env.capture(),
+        name: Some(name.clone()),
+        parameters,
+        body,
+    };
+    declare(&name, ChaiObject::Function(function), env)
+}
+
+fn unexported(statement: &Statement) -> &Statement {
+    match statement {
+        Statement::Export(statement) => statement,
+        statement => statement,
+    }
+}
+
+// Function declarations are visible to the whole block they appear in, so
+// they can be called before their definition and can call each other. A
+// `const` anywhere in the same block can't share their name.
+fn hoist_functions(statements: &[Statement], env: &mut Enviornment) -> ChaiObject {
+    let constants: Vec<String> = statements
+        .iter()
+        .filter(|statement| matches!(unexported(statement), Statement::Const(_, _)))
+        .flat_map(|statement| statement.declared_names())
+        .collect();
+
+    for statement in statements {
+        if let Statement::Function(name, parameters, body) = unexported(statement) {
+            if constants.contains(name) {
+                return redeclared_constant(name);
+            }
+            let result = declare_function(name.clone(), parameters.clone(), body.clone(), env);
+            if is_error(&result) {
+                return result;
+            }
+        }
+    }
+    NULL
+}
+
+// Evaluates the module at `path` in its own scope, or returns it from the
+// cache when it was already imported
+fn load_module(path: &str, from: FileId, std: &mut Std) -> Result<Module, ChaiObject> {
+    let (canonical, path) = std.modules.resolve(from, path).map_err(|err| error(&err))?;
+    if let Some(module) = std.modules.cached(&canonical) {
+        return Ok(module);
+    }
+    std.modules
+        .check_cycle(&canonical, &path)
+        .map_err(|err| error(&err))?;
+
+    let file = std.modules.sources.load(&path).map_err(|err| error(&err))?;
+    let mut parser = Parser::new(std.modules.sources.lexer(file));
+    let program = parser
+        .parse_program()
+        .map_err(|err| error(&err.render(&std.modules.sources)))?;
+    let exported: Vec<String> = match &program {
+        Statement::Program(statements) => statements
+            .iter()
+            .filter(|statement| matches!(statement, Statement::Export(_)))
+            .flat_map(|statement| statement.declared_names())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    std.modules.start(canonical.clone(), path.clone());
+    let mut module_env = Enviornment::new();
+    let result = eval(program, &mut module_env, std);
+    if is_error(&result) {
+        std.modules.finish(canonical, None);
+        return Err(result);
+    }
+
+    let mut exports = BTreeMap::new();
+    for name in exported {
+        if let Some(mut value) = module_env.get(&name) {
+            Enviornment::escape(&mut value);
+            exports.insert(name, value);
+        }
+    }
+
+    let module = Module { path, exports };
+    std.modules.finish(canonical, Some(module.clone()));
+    Ok(module)
+}
+
+fn eval_import(
+    path: String,
+    names: ImportNames,
+    from: FileId,
+    env: &mut Enviornment,
+    std: &mut Std,
+) -> ChaiObject {
+    let module = match load_module(&path, from, std) {
+        Ok(module) => module,
+        Err(err) => return err,
+    };
+
+    match names {
+        ImportNames::Module(name) => return declare(&name, ChaiObject::Module(module), env),
+        ImportNames::Names(names) => {
+            for name in names {
+                let result = match module.exports.get(&name) {
+                    Some(value) => declare(&name, value.clone(), env),
+                    None => error(&format!("Module {} has no export {}", module.path, name)),
+                };
+                if is_error(&result) {
+                    return result;
+                }
+            }
+        }
+    }
+    NULL
+}
+
 fn eval_block_statment(
     statements: Vec<Statement>,
     env: &mut Enviornment,
     std: &mut Std,
 ) -> ChaiObject {
     let mut result = ChaiObject::Null;
+    // Bindings made inside a block are dropped at its closing brace
+    env.enter_scope();
+    let hoisted = hoist_functions(&statements, env);
+    if is_error(&hoisted) {
+        env.exit_scope();
+        return hoisted;
+    }
     for statement in statements {
         result = eval(statement, env, std);
 
@@ -361,20 +1426,25 @@ fn eval_block_statment(
             println!("{}", print);
         }
 
-        if result != ChaiObject::Null
-            && ((std::mem::discriminant(&ChaiObject::Return(ChaiObject::Null.into()))
-                == std::mem::discriminant(&result))
-                || (std::mem::discriminant(&ChaiObject::Error("".into()))
-                    == std::mem::discriminant(&result)))
+        if let ChaiObject::Return(_)
+        | ChaiObject::Error(_)
+        | ChaiObject::Break
+        | ChaiObject::Continue = result
         {
-            return result;
+            break;
         }
     }
+    Enviornment::escape(&mut result);
+    env.exit_scope();
     result
 }
 
 fn eval_program(statements: Vec<Statement>, env: &mut Enviornment, std: &mut Std) -> ChaiObject {
     let mut result = ChaiObject::Null;
+    let hoisted = hoist_functions(&statements, env);
+    if is_error(&hoisted) {
+        return hoisted;
+    }
     for statement in statements {
         result = eval(statement, env, std);
 
@@ -393,3 +1463,938 @@ fn eval_program(statements: Vec<Statement>, env: &mut Enviornment, std: &mut Std
 
     result
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::sourcemap::SourceMap;
+
+    fn run(src: &str) -> ChaiObject {
+        let mut parser = Parser::new(Lexer::new(src.into()));
+        let program = parser.parse_program().expect("program should parse");
+        eval(program, &mut Enviornment::new(), &mut Std::load())
+    }
+
+    fn array(items: Vec<ChaiObject>) -> ChaiObject {
+        ChaiObject::Array(Rc::new(items))
+    }
+
+    fn integers(values: &[i32]) -> ChaiObject {
+        array(values.iter().map(|v| ChaiObject::Integer(*v)).collect())
+    }
+
+    #[test]
+    fn else_if_chain() {
+        let src = "
+            let grade = fn(score) {
+                if (score > 89) {
+                    return \"A\";
+                } else if (score > 79) {
+                    return \"B\";
+                } else if (score > 69) {
+                    return \"C\";
+                } else {
+                    return \"F\";
+                }
+            };
+            grade(95) + grade(85) + grade(75) + grade(10);
+        ";
+        assert_eq!(run(src), ChaiObject::String("ABCF".into()));
+    }
+
+    #[test]
+    fn for_in_loops() {
+        let src = "
+            let total = 0;
+            for (x in [1, 2, 3]) { total = total + x; }
+            for (i, x in [10, 20]) { total = total + i * x; }
+            for (i in range(4)) { total = total + i; }
+            let word = \"\";
+            for (ch in \"abc\") { word = ch + word; };
+            [word, total];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::String("cba".into()),
+                ChaiObject::Integer(32)
+            ])
+        );
+    }
+
+    #[test]
+    fn for_in_loop_variable_is_scoped() {
+        assert_eq!(
+            run("let x = 7; for (x in [1, 2]) { } x;"),
+            ChaiObject::Integer(7)
+        );
+        assert_eq!(
+            run("for (i, x in [1, 2]) { } x;"),
+            error("Variable : x not found")
+        );
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let src = "
+            let i = 0;
+            let odd = 0;
+            while (true) {
+                i = i + 1;
+                if (i > 9) { break; }
+                if (i / 2 * 2 == i) { continue; }
+                odd = odd + i;
+            };
+            let found = 0;
+            for (x in [3, 8, 5, 12]) {
+                if (x < 6) { continue; }
+                found = x;
+                break;
+            };
+            [odd, found];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![ChaiObject::Integer(25), ChaiObject::Integer(8)])
+        );
+    }
+
+    #[test]
+    fn return_from_nested_loops_inside_function() {
+        let src = "
+            let find = fn(grid, target) {
+                let i = 0;
+                while (i < len(grid)) {
+                    let j = 0;
+                    while (j < len(grid[i])) {
+                        if (grid[i][j] == target) { return [i, j]; }
+                        j = j + 1;
+                    }
+                    i = i + 1;
+                }
+                return -1;
+            };
+            let grid = [[1, 2], [3, 4]];
+            [find(grid, 4), find(grid, 5)];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                array(vec![ChaiObject::Integer(1), ChaiObject::Integer(1)]),
+                ChaiObject::Integer(-1),
+            ])
+        );
+    }
+
+    #[test]
+    fn error_inside_while_body_stops_the_loop() {
+        let src = "
+            let f = fn() {
+                while (true) {
+                    while (true) {
+                        missing;
+                    }
+                }
+            };
+            f();
+        ";
+        assert_eq!(run(src), error("Variable : missing not found"));
+    }
+
+    #[test]
+    fn named_functions_are_hoisted() {
+        let src = "
+            let result = [is_even(10), is_odd(7)];
+            fn is_even(n) {
+                if (n == 0) { return true; }
+                is_odd(n - 1)
+            }
+            fn is_odd(n) {
+                if (n == 0) { return false; }
+                is_even(n - 1)
+            }
+            result;
+        ";
+        assert_eq!(run(src), array(vec![TRUE, TRUE]));
+    }
+
+    #[test]
+    fn named_function_records_its_name() {
+        assert_eq!(
+            run("fn double(x) { x * 2 } double + 1;"),
+            error("Type mismatch : Function double + Integer")
+        );
+    }
+
+    #[test]
+    fn hash_literals_and_builtins() {
+        let src = "
+            let h = {\"a\": 1, 2: \"b\", true: [1]};
+            let h = delete(h, 2);
+            [h[\"a\"], h[true], len(h), keys(h), values(h), has_key(h, 2)];
+        ";
+        let s = |string: &str| ChaiObject::String(string.into());
+        let one = ChaiObject::Integer(1);
+        assert_eq!(
+            run(src),
+            array(vec![
+                one.clone(),
+                array(vec![one.clone()]),
+                ChaiObject::Integer(2),
+                array(vec![TRUE, s("a")]),
+                array(vec![array(vec![one.clone()]), one]),
+                FALSE,
+            ])
+        );
+        assert_eq!(run("{[1]: 2}"), error("Unusable as hash key : Array"));
+    }
+
+    #[test]
+    fn index_assignment() {
+        let src = "
+            let grid = [[0, 0], [0, 0]];
+            grid[1][0] = 5;
+            let h = {\"a\": [1, 2]};
+            h[\"a\"][1] = 3;
+            h[\"b\"] = true;
+            [grid, h];
+        ";
+        let mut hash = BTreeMap::new();
+        hash.insert(
+            HashKey::String("a".into()),
+            array(vec![ChaiObject::Integer(1), ChaiObject::Integer(3)]),
+        );
+        hash.insert(HashKey::String("b".into()), TRUE);
+        assert_eq!(
+            run(src),
+            array(vec![
+                array(vec![
+                    array(vec![ChaiObject::Integer(0), ChaiObject::Integer(0)]),
+                    array(vec![ChaiObject::Integer(5), ChaiObject::Integer(0)]),
+                ]),
+                ChaiObject::Hash(hash.into()),
+            ])
+        );
+        assert_eq!(run("let a = [1]; a[1] = 2;"), error("Array out of bound"));
+    }
+
+    #[test]
+    fn compound_and_chained_assignment() {
+        let src = "
+            let a = 1;
+            let b = 1;
+            let xs = [10, 20];
+            a = b = 0;
+            a += 7;
+            a *= 3;
+            a -= 1;
+            a /= 4;
+            b %= 3;
+            xs[1] += 5;
+            [a, b, xs[1], 17 % 5];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::Integer(5),
+                ChaiObject::Integer(0),
+                ChaiObject::Integer(25),
+                ChaiObject::Integer(2),
+            ])
+        );
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_its_target_once() {
+        let src = "
+            let i = 0;
+            fn next() { i += 1; i }
+            let xs = [0, 0, 0];
+            xs[next()] += 10;
+            [i, xs];
+        ";
+        assert_eq!(run(src).to_string(), "[1,[0,10,0]]");
+        assert_eq!(
+            run("let h = {}; h[\"a\"] += 1;"),
+            error("Key not found : a")
+        );
+    }
+
+    #[test]
+    fn destructuring_let_and_parameters() {
+        let src = "
+            let [a, b, ...rest] = [1, 2, 3, 4];
+            let {name, age} = {\"name\": \"chai\", \"age\": 3};
+            let swap = fn([x, y]) { [y, x] };
+            let [first, second] = swap([a, b]);
+            [first, second, rest, name, age];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::Integer(2),
+                ChaiObject::Integer(1),
+                array(vec![ChaiObject::Integer(3), ChaiObject::Integer(4)]),
+                ChaiObject::String("chai".into()),
+                ChaiObject::Integer(3),
+            ])
+        );
+    }
+
+    #[test]
+    fn destructuring_shape_mismatch() {
+        assert_eq!(
+            run("let [a, b, c] = [1, 2];"),
+            error("Pattern [a, b, c] doesn't match an array of 2 elements")
+        );
+        assert_eq!(
+            run("let [a, ...rest] = 1;"),
+            error("Pattern [a, ...rest] doesn't match Integer")
+        );
+        assert_eq!(
+            run("let {name, age} = {\"name\": 1};"),
+            error("Pattern {name, age} : key age not found")
+        );
+    }
+
+    #[test]
+    fn default_and_named_arguments() {
+        let src = "
+            fn area(w, h = w, scale = 1) { w * h * scale }
+            [area(3), area(2, 5), area(2, scale: 10), area(h: 4, w: 1)];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::Integer(9),
+                ChaiObject::Integer(10),
+                ChaiObject::Integer(40),
+                ChaiObject::Integer(4),
+            ])
+        );
+    }
+
+    #[test]
+    fn arity_errors() {
+        let src = "fn add(a, b) { a + b }";
+        assert_eq!(
+            run(&format!("{} add(1);", src)),
+            error("Function add missing argument for b")
+        );
+        assert_eq!(
+            run(&format!("{} add(1, 2, 3);", src)),
+            error("Function add expects at most 2 arguments, found 3")
+        );
+        assert_eq!(
+            run(&format!("{} add(1, a: 2);", src)),
+            error("Function add got multiple values for a")
+        );
+        assert_eq!(
+            run(&format!("{} add(1, c: 2);", src)),
+            error("Function add has no parameter c")
+        );
+        assert_eq!(
+            run(&format!("{} add(a: 1, a: 2, b: 3);", src)),
+            error("Function add got multiple values for a")
+        );
+        assert_eq!(
+            run("fn count(...rest) { len(rest) } count(rest: 1);"),
+            error("Function count has no parameter rest")
+        );
+        assert_eq!(
+            run("struct Point { x, y } Point(x: 1, x: 2, y: 3);"),
+            error("Point got multiple values for x")
+        );
+    }
+
+    #[test]
+    fn rest_parameters_and_spread() {
+        let src = "
+            fn count(first, ...rest) { [first, len(rest)] }
+            let a = [1, 2];
+            let b = [...a, 3, ...range(4, 6)];
+            [count(...b), count(1), b];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                integers(&[1, 4]),
+                integers(&[1, 0]),
+                integers(&[1, 2, 3, 4, 5]),
+            ])
+        );
+        assert_eq!(run("[...1]"), error("Cannot spread Integer"));
+    }
+
+    #[test]
+    fn pipeline_operator() {
+        let src = "
+            fn double(x) { x * 2 }
+            let xs = [1, 2] |> push(3) |> pop |> push(4);
+            [xs, 1 + 2 |> double, len(xs) |> double];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                integers(&[1, 2, 4]),
+                ChaiObject::Integer(6),
+                ChaiObject::Integer(6),
+            ])
+        );
+    }
+
+    #[test]
+    fn ranges_and_slices() {
+        let src = "
+            let total = 0;
+            for (i in 1..=4) { total += i; };
+            let arr = [1, 2, 3, 4, 5];
+            let s = \"chailang\";
+            [total, arr[1..3], arr[3..], arr[..0], s[..4], s[4..], [...0..3]];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::Integer(10),
+                integers(&[2, 3]),
+                integers(&[4, 5]),
+                integers(&[]),
+                ChaiObject::String("chai".into()),
+                ChaiObject::String("lang".into()),
+                integers(&[0, 1, 2]),
+            ])
+        );
+        assert_eq!(run("[1, 2][1..3]"), error("Slice out of bound"));
+        assert_eq!(
+            run("let s = \"héllo\"; [len(s), s[..len(s)], s[1..2]];").to_string(),
+            "[5,héllo,é]"
+        );
+        assert_eq!(
+            run("let last = 0; for (i in 2147483640..) { last = i; if (i == 2147483645) { break; } }; last;"),
+            ChaiObject::Integer(2147483645)
+        );
+        assert_eq!(
+            run("for (i in 2147483640..) { i; }"),
+            error("Range overflowed past 2147483647")
+        );
+    }
+
+    #[test]
+    fn method_calls_and_field_access() {
+        let src = "
+            fn add(a, b) { a + b }
+            let person = {\"name\": \"chai\", \"greet\": fn(x) { \"hi \" + x }};
+            let arr = [1, 2, 3].push(4).pop().push(5);
+            [arr.len(), \"chai\".upper(), 1.add(2), person.name, person.greet(\"you\")];
+        ";
+        assert_eq!(
+            run(src),
+            array(vec![
+                ChaiObject::Integer(4),
+                ChaiObject::String("CHAI".into()),
+                ChaiObject::Integer(3),
+                ChaiObject::String("chai".into()),
+                ChaiObject::String("hi you".into()),
+            ])
+        );
+        assert_eq!(
+            run("1.missing()"),
+            error("No method missing found for Integer")
+        );
+    }
+
+    #[test]
+    fn match_expressions() {
+        let src = "
+            fn describe(value) {
+                match (value) {
+                    0 => \"zero\",
+                    -1 => \"minus one\",
+                    \"x\" | \"y\" => \"axis\",
+                    [a, b] => \"pair \" + a,
+                    [first, ...rest] => {
+                        let count = len(rest);
+                        \"list of \" + first
+                    }
+                    {name} => name,
+                    n if n > 10 => \"big\",
+                    _ => \"other\"
+                }
+            }
+            [describe(0), describe(-1), describe(\"y\"), describe([\"a\", \"b\"]),
+             describe([\"c\"]), describe({\"name\": \"chai\"}), describe(11), describe(5)];
+        ";
+        let strings = |values: &[&str]| {
+            array(
+                values
+                    .iter()
+                    .map(|v| ChaiObject::String(v.to_string()))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            run(src),
+            strings(&[
+                "zero",
+                "minus one",
+                "axis",
+                "pair a",
+                "list of c",
+                "chai",
+                "big",
+                "other"
+            ])
+        );
+    }
+
+    #[test]
+    fn match_bindings_are_scoped_and_unmatched_is_an_error() {
+        assert_eq!(
+            run("let n = 1; match (5) { n if n > 3 => n }; n;"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("match (5) { 1 | 2 => true }"),
+            error("No match arm matches Integer")
+        );
+    }
+
+    #[test]
+    fn struct_records() {
+        let point = "struct Point { x, y }";
+        assert_eq!(
+            run(&format!("{} let p = Point(1, 2); p.x + p.y;", point)),
+            ChaiObject::Integer(3)
+        );
+        assert_eq!(
+            run(&format!(
+                "{} let p = Point {{ y: 2, x: 1 }}; p == Point(1, y: 2);",
+                point
+            )),
+            ChaiObject::Boolean(true)
+        );
+        assert_eq!(
+            run(&format!("{} let p = Point(1, 2); p.x = 5; p;", point)).to_string(),
+            "Point { x: 5, y: 2 }"
+        );
+        assert_eq!(
+            run(&format!(
+                "{} match (Point(1, 2)) {{ {{x, y}} => x * 10 + y }}",
+                point
+            )),
+            ChaiObject::Integer(12)
+        );
+        assert_eq!(
+            run(&format!("{} Point(1);", point)),
+            error("Point missing field y")
+        );
+        assert_eq!(
+            run(&format!("{} Point {{ x: 1, z: 2 }};", point)),
+            error("Point has no field z")
+        );
+        assert_eq!(
+            run(&format!("{} let p = Point(1, 2); p.z = 3;", point)),
+            error("Point has no field z")
+        );
+    }
+
+    #[test]
+    fn tagged_enums() {
+        let shape = "enum Shape { Circle(r), Rect(w, h), Empty }";
+        assert_eq!(
+            run(&format!("{} Shape.Rect(2, 3);", shape)).to_string(),
+            "Shape.Rect(2, 3)"
+        );
+        assert_eq!(
+            run(&format!("{} Shape.Circle(1) == Shape.Circle(r: 1);", shape)),
+            ChaiObject::Boolean(true)
+        );
+        assert_eq!(
+            run(&format!("{} type_of(Shape.Empty);", shape)),
+            ChaiObject::String("Shape.Empty".into())
+        );
+        assert_eq!(
+            run(&format!(
+                "{} let area = fn(s) {{ match (s) {{ Circle(r) => 3 * r * r, Shape.Rect(w, h) => w * h, Shape.Empty => 0 }} }}; [area(Shape.Circle(2)), area(Shape.Rect(2, 3)), area(Shape.Empty)];",
+                shape
+            )),
+            array(vec![
+                ChaiObject::Integer(12),
+                ChaiObject::Integer(6),
+                ChaiObject::Integer(0)
+            ])
+        );
+        assert_eq!(
+            run(&format!(
+                "{} enum Color {{ Red }} Shape.Empty == Color.Red;",
+                shape
+            )),
+            error("Type mismatch : Shape.Empty == Color.Red")
+        );
+        assert_eq!(
+            run("enum S { A, B(x) } match (S.B(1)) { A => \"wrong\", _ => \"ok\" }"),
+            ChaiObject::String("ok".into())
+        );
+        assert_eq!(
+            run("enum S { A, B(x) } match (S.A) { B(x) => x, A => \"a\" }"),
+            ChaiObject::String("a".into())
+        );
+        assert_eq!(
+            run("enum S { A } match (S.A) { other => type_of(other) }"),
+            ChaiObject::String("S.A".into())
+        );
+        assert_eq!(
+            run(&format!("{} Shape.Circle(1, 2);", shape)),
+            error("Shape.Circle expects at most 1 fields, found 2")
+        );
+        assert_eq!(
+            run(&format!("{} Shape.Square;", shape)),
+            error("Shape has no variant Square")
+        );
+    }
+
+    fn run_files(files: &[(&str, &str)]) -> ChaiObject {
+        let root = std::env::temp_dir().join(format!(
+            "chai-modules-{}-{}",
+            std::process::id(),
+            files[0].0.replace('/', "-")
+        ));
+        for (path, src) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, src).unwrap();
+        }
+
+        let mut sources = SourceMap::new();
+        let file = sources
+            .load(root.join(files[0].0).to_str().unwrap())
+            .unwrap();
+        let mut parser = Parser::new(sources.lexer(file));
+        let program = parser.parse_program().expect("program should parse");
+        let result = eval(
+            program,
+            &mut Enviornment::new(),
+            &mut Std::with_sources(sources),
+        );
+        std::fs::remove_dir_all(root).unwrap();
+        result
+    }
+
+    #[test]
+    fn modules_import_exported_bindings() {
+        let utils =
+            "fn helper(x) { x * 2 } export fn double(x) { helper(x) } export let base = 10;";
+        assert_eq!(
+            run_files(&[
+                (
+                    "main.ch",
+                    "import \"lib/utils.ch\" as utils; utils.double(utils.base);"
+                ),
+                ("lib/utils.ch", utils),
+            ]),
+            ChaiObject::Integer(20)
+        );
+        assert_eq!(
+            run_files(&[
+                (
+                    "selective.ch",
+                    "import { double } from \"lib/utils.ch\"; double(4);"
+                ),
+                ("lib/utils.ch", utils),
+            ]),
+            ChaiObject::Integer(8)
+        );
+        let result = run_files(&[
+            (
+                "private.ch",
+                "import \"lib/utils.ch\" as utils; utils.helper;",
+            ),
+            ("lib/utils.ch", utils),
+        ]);
+        assert!(result
+            .to_string()
+            .ends_with("lib/utils.ch has no export helper"));
+        assert_eq!(
+            run_files(&[
+                (
+                    "constant.ch",
+                    "const double = 1; import { double } from \"lib/utils.ch\";"
+                ),
+                ("lib/utils.ch", utils),
+            ]),
+            error("Cannot redeclare constant double")
+        );
+        assert_eq!(
+            run_files(&[
+                (
+                    "alias.ch",
+                    "const utils = 1; import \"lib/utils.ch\" as utils;"
+                ),
+                ("lib/utils.ch", utils),
+            ]),
+            error("Cannot redeclare constant utils")
+        );
+    }
+
+    #[test]
+    fn modules_resolve_relative_paths_and_report_cycles() {
+        assert_eq!(
+            run_files(&[
+                ("relative.ch", "import { a } from \"lib/a.ch\"; a;"),
+                (
+                    "lib/a.ch",
+                    "import { b } from \"b.ch\"; export let a = b + 1;"
+                ),
+                ("lib/b.ch", "export let b = 1;"),
+            ]),
+            ChaiObject::Integer(2)
+        );
+        let result = run_files(&[
+            ("cycle.ch", "import \"lib/c.ch\" as c;"),
+            ("lib/c.ch", "import \"../cycle.ch\" as main;"),
+        ]);
+        assert!(result.to_string().contains("Import cycle : "));
+        assert!(result.to_string().ends_with("lib/../cycle.ch"));
+    }
+
+    #[test]
+    fn short_lambdas() {
+        assert_eq!(
+            run("let double = |x| x * 2; double(21);"),
+            ChaiObject::Integer(42)
+        );
+        assert_eq!(
+            run("let apply = fn(f, x) { f(x) }; apply(|n| n + 1, 1) + (|| 5)();"),
+            ChaiObject::Integer(7)
+        );
+        assert_eq!(
+            run("let add = |a, b = 10| { let c = a + b; c }; add(1) + add(1, 2);"),
+            ChaiObject::Integer(14)
+        );
+        assert_eq!(run("3 |> |x| x * x;"), ChaiObject::Integer(9));
+    }
+
+    #[test]
+    fn constants_cannot_be_reassigned_or_redeclared() {
+        assert_eq!(run("const LIMIT = 10; LIMIT * 2;"), ChaiObject::Integer(20));
+        assert_eq!(
+            run("const LIMIT = 10; LIMIT = 11;"),
+            error("Cannot assign to constant LIMIT")
+        );
+        assert_eq!(
+            run("const LIMIT = 10; LIMIT += 1;"),
+            error("Cannot assign to constant LIMIT")
+        );
+        assert_eq!(
+            run("const ITEMS = [1]; ITEMS[0] = 2;"),
+            error("Cannot assign to constant ITEMS")
+        );
+        assert_eq!(
+            run("const LIMIT = 10; let LIMIT = 11;"),
+            error("Cannot redeclare constant LIMIT")
+        );
+        assert_eq!(
+            run("const [a, b] = [1, 2]; let f = fn() { b = 3; }; f();"),
+            error("Cannot assign to constant b")
+        );
+        assert_eq!(
+            run("const LIMIT = 10; let f = fn() { let LIMIT = 1; LIMIT }; f();"),
+            ChaiObject::Integer(1)
+        );
+    }
+
+    #[test]
+    fn declarations_cannot_rebind_a_constant() {
+        assert_eq!(
+            run("const f = 1; print(f); fn f() { 2 } f;"),
+            error("Cannot redeclare constant f")
+        );
+        assert_eq!(
+            run("fn f() { 2 } const f = 1;"),
+            error("Cannot redeclare constant f")
+        );
+        assert_eq!(
+            run("const Point = 1; struct Point { x, y }"),
+            error("Cannot redeclare constant Point")
+        );
+        assert_eq!(
+            run("const Shape = 1; enum Shape { Empty }"),
+            error("Cannot redeclare constant Shape")
+        );
+        assert_eq!(
+            run("const f = 1; if (true) { fn f() { 2 } f() };"),
+            ChaiObject::Integer(2)
+        );
+    }
+
+    #[test]
+    fn blocks_open_a_nested_scope() {
+        assert_eq!(
+            run("let x = 1; if (true) { let y = 2; x = x + y; }; y;"),
+            error("Variable : y not found")
+        );
+        assert_eq!(
+            run("let x = 1; if (true) { let x = 5; x = x + 1; }; x;"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("let total = 0; let i = 0; while (i < 3) { let step = i + 1; total += step; i += 1; }; total;"),
+            ChaiObject::Integer(6)
+        );
+        assert_eq!(
+            run("let sum = 0; for (n in [1, 2, 3]) { sum += n; }; [sum, n];"),
+            error("Variable : n not found")
+        );
+        assert_eq!(run("missing = 1;"), error("Variable missing not found"));
+    }
+
+    #[test]
+    fn closures_capture_their_defining_scope() {
+        assert_eq!(
+            run("let counter = fn() { let count = 0; || { count += 1; count } }; let next = counter(); next(); next(); next();"),
+            ChaiObject::Integer(3)
+        );
+        assert_eq!(
+            run("fn counter() { let count = 0; || { count += 1; count } } let a = counter(); let b = counter(); a(); a(); b();"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("let add = |a| |b| a + b; let plus = add(5); plus(2);"),
+            ChaiObject::Integer(7)
+        );
+        assert_eq!(
+            run("let x = 1; let get = || x; let f = fn() { let x = 2; get() }; f();"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("let f = fn() { y }; let g = fn() { let y = 2; f() }; g();"),
+            error("Variable : y not found")
+        );
+        assert_eq!(
+            run("let fs = []; for (i, x in [\"a\", \"b\", \"c\"]) { fs = push(fs, || [x, i]); }; [fs[0](), fs[2]()];").to_string(),
+            "[[a,0],[c,2]]"
+        );
+    }
+
+    #[test]
+    fn scopes_are_freed_when_their_functions_are() {
+        let sources = [
+            "fn helper() { big } let big = [1, 2, 3]; let other = || helper(); helper();",
+            "let big = [1, 2, 3]; let h = {\"f\": || big}; h.f();",
+            "let big = [1, 2, 3]; let a = [0, [|| big]]; a[1][0]();",
+            "let big = [1, 2, 3]; let h = {}; h[\"f\"] = || big; h.f();",
+            "struct R { f } enum E { V(f) } let big = [1, 2, 3]; let r = R(|| big); let v = E.V(r.f); r.f();",
+        ];
+        for src in sources {
+            let program = Parser::new(Lexer::new(src.into()))
+                .parse_program()
+                .expect("program should parse");
+            let mut env = Enviornment::new();
+            env.enter_scope();
+            let block = env.downgrade();
+            assert_eq!(
+                eval(program, &mut env, &mut Std::load()).to_string(),
+                "[1,2,3]"
+            );
+            env.exit_scope();
+            assert!(block.upgrade().is_none(), "{} leaked its scope", src);
+        }
+
+        assert_eq!(
+            run("fn mk() { let big = 1; let h = {\"f\": || big}; h } let o = mk(); o.f();"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("fn mk() { let big = 2; let a = [|| big]; a } mk()[0]();"),
+            ChaiObject::Integer(2)
+        );
+
+        assert_eq!(
+            run("fn counter() { let count = 0; let next = || { count += 1; count }; next } let next = counter(); next(); next();"),
+            ChaiObject::Integer(2)
+        );
+        assert_eq!(
+            run("let total = 0; for (i in 0..3) { fn helper() { i } total += helper(); } total;"),
+            ChaiObject::Integer(3)
+        );
+    }
+
+    #[test]
+    fn function_values_share_their_body() {
+        let functions = match run("fn f() { 1 } let g = f; [f, g, f];") {
+            ChaiObject::Array(functions) => functions,
+            object => panic!("expected an array, found {}", object),
+        };
+        let body = |object: &ChaiObject| match object {
+            ChaiObject::Function(function) => function.body.clone(),
+            object => panic!("expected a function, found {}", object),
+        };
+        assert!(Rc::ptr_eq(&body(&functions[0]), &body(&functions[1])));
+        assert!(Rc::ptr_eq(&body(&functions[0]), &body(&functions[2])));
+    }
+
+    #[test]
+    fn functions_update_variables_of_enclosing_scopes() {
+        assert_eq!(
+            run("let total = 0; let add = fn(x) { total = total + x; }; add(2); add(3); total;"),
+            ChaiObject::Integer(5)
+        );
+        assert_eq!(
+            run("let items = [0, 0]; fn mark(i) { items[i] = 1; } mark(1); items;"),
+            array(vec![ChaiObject::Integer(0), ChaiObject::Integer(1)])
+        );
+        assert_eq!(
+            run("let seen = 0; for (n in 1..4) { let record = || { seen += n; }; record(); }; seen;"),
+            ChaiObject::Integer(6)
+        );
+        assert_eq!(
+            run("let x = 1; let f = fn() { let x = 10; x = 20; }; f(); x;"),
+            ChaiObject::Integer(1)
+        );
+        assert_eq!(
+            run("let outer = fn() { let n = 0; let inner = fn() { n += 1; }; inner(); inner(); n }; outer();"),
+            ChaiObject::Integer(2)
+        );
+    }
+
+    #[test]
+    fn large_arrays_are_indexed_and_updated_in_place() {
+        let src = "
+            let xs = [...0..10000];
+            let n = len(xs);
+            let total = 0;
+            for (i in 0..n) {
+                xs[i] = xs[i] * 2;
+                total += xs[i];
+            };
+            total;
+        ";
+        assert_eq!(run(src), ChaiObject::Integer(99990000));
+        let src = "
+            let xs = [...0..10000];
+            let total = 0;
+            let i = 0;
+            while (i < len(xs)) {
+                xs[i] = xs[i] * 2;
+                total += xs[i];
+                i += 1;
+            };
+            total;
+        ";
+        assert_eq!(run(src), ChaiObject::Integer(99990000));
+        assert_eq!(
+            run("let xs = [1, 2]; let ys = xs; ys[0] = 5; let zs = push(xs, 3); [xs, ys, zs];")
+                .to_string(),
+            "[[1,2],[5,2],[1,2,3]]"
+        );
+        assert_eq!(
+            run("let h = {\"p\": {\"x\": 1}}; h[\"p\"].x = 2; h.p.y = 3; h.p;").to_string(),
+            "{x:2,y:3}"
+        );
+        assert_eq!(
+            run("let h = {}; h[\"a\"][\"b\"] = 1;"),
+            error("Key not found : a")
+        );
+    }
+}
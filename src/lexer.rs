@@ -1,3 +1,4 @@
+use crate::sourcemap::FileId;
 use crate::token::Token;
 use crate::token::TokenType;
 use crate::tokeninfo::TokenInfo;
@@ -5,6 +6,7 @@ use crate::tokeninfo::TokenInfo;
 #[derive(Debug, Clone, PartialEq)]
 pub struct Lexer {
     input: String,
+    file: FileId,
     position: usize,
     read_position: usize,
     line: usize,
@@ -14,8 +16,13 @@ pub struct Lexer {
 
 impl Lexer {
     pub fn new(input: String) -> Self {
+        Lexer::with_file(input, 0)
+    }
+
+    pub fn with_file(input: String, file: FileId) -> Self {
         let mut lexer = Lexer {
             input,
+            file,
             position: 0,
             read_position: 0,
             line: 1,
@@ -37,10 +44,13 @@ impl Lexer {
     }
 
     fn peek_char(&mut self) -> u8 {
-        if self.read_position >= self.input.len() {
-            0
-        } else {
-            return self.input.as_bytes()[self.read_position];
+        self.peek_char_at(0)
+    }
+
+    fn peek_char_at(&self, offset: usize) -> u8 {
+        match self.input.as_bytes().get(self.read_position + offset) {
+            Some(ch) => *ch,
+            None => 0,
         }
     }
 
@@ -49,16 +59,32 @@ impl Lexer {
             token_type,
             token_info: TokenInfo {
                 litertal: self.input[start..end].to_string(),
+                file: self.file,
                 line: self.line,
                 col: (start + 1 - self.col),
             },
         }
     }
 
+    // Operators which have a compound assignment form, eg. `+` and `+=`
+    fn create_operator_token(&mut self, operator: TokenType, assign: TokenType) -> Token {
+        if self.peek_char() == b'=' {
+            self.read_char();
+            self.create_token(assign, self.position - 1, self.read_position)
+        } else {
+            self.create_token(operator, self.position, self.read_position)
+        }
+    }
+
     fn create_eof(&self) -> Token {
         Token {
             token_type: TokenType::Eof,
-            token_info: TokenInfo::default(),
+            token_info: TokenInfo {
+                litertal: "".into(),
+                file: self.file,
+                line: self.line,
+                col: (self.position + 1 - self.col),
+            },
         }
     }
 
@@ -73,11 +99,11 @@ impl Lexer {
     }
 
     fn is_letter(ch: u8) -> bool {
-        ch >= b'A' && ch <= b'Z' || ch >= b'a' && ch <= b'z' || ch == b'_'
+        ch.is_ascii_alphabetic() || ch == b'_'
     }
 
     fn is_digit(ch: u8) -> bool {
-        ch >= b'0' && ch <= b'9'
+        ch.is_ascii_digit()
     }
 
     fn read_identifier(&mut self) -> (usize, usize) {
@@ -105,6 +131,13 @@ impl Lexer {
                     self.read_char();
                     token =
                         self.create_token(TokenType::Equal, self.position - 1, self.read_position);
+                } else if self.peek_char() == b'>' {
+                    self.read_char();
+                    token = self.create_token(
+                        TokenType::FatArrow,
+                        self.position - 1,
+                        self.read_position,
+                    );
                 } else {
                     token = self.create_token(TokenType::Assign, self.position, self.read_position);
                 }
@@ -121,6 +154,33 @@ impl Lexer {
                     token = self.create_token(TokenType::Bang, self.position, self.read_position);
                 }
             }
+            ':' => {
+                token = self.create_token(TokenType::Colon, self.position, self.read_position);
+            }
+            '.' if self.peek_char() == b'.' => {
+                let token_type = match self.peek_char_at(1) {
+                    b'.' => TokenType::Ellipsis,
+                    b'=' => TokenType::DotDotEqual,
+                    _ => TokenType::DotDot,
+                };
+                let start = self.position;
+                self.read_char();
+                if token_type != TokenType::DotDot {
+                    self.read_char();
+                }
+                token = self.create_token(token_type, start, self.read_position);
+            }
+            '|' if self.peek_char() == b'>' => {
+                self.read_char();
+                token =
+                    self.create_token(TokenType::Pipeline, self.position - 1, self.read_position);
+            }
+            '.' => {
+                token = self.create_token(TokenType::Dot, self.position, self.read_position);
+            }
+            '|' => {
+                token = self.create_token(TokenType::Bar, self.position, self.read_position);
+            }
             ';' => {
                 token = self.create_token(TokenType::Semicolon, self.position, self.read_position);
             }
@@ -133,18 +193,13 @@ impl Lexer {
             ',' => {
                 token = self.create_token(TokenType::Comma, self.position, self.read_position);
             }
-            '+' => {
-                token = self.create_token(TokenType::Plus, self.position, self.read_position);
-            }
-            '-' => {
-                token = self.create_token(TokenType::Minus, self.position, self.read_position);
-            }
+            '+' => token = self.create_operator_token(TokenType::Plus, TokenType::PlusAssign),
+            '-' => token = self.create_operator_token(TokenType::Minus, TokenType::MinusAssign),
             '*' => {
-                token = self.create_token(TokenType::Asterisk, self.position, self.read_position);
-            }
-            '/' => {
-                token = self.create_token(TokenType::Slash, self.position, self.read_position);
+                token = self.create_operator_token(TokenType::Asterisk, TokenType::AsteriskAssign)
             }
+            '/' => token = self.create_operator_token(TokenType::Slash, TokenType::SlashAssign),
+            '%' => token = self.create_operator_token(TokenType::Percent, TokenType::PercentAssign),
             '<' => {
                 token = self.create_token(TokenType::Lessthan, self.position, self.read_position);
             }
@@ -175,13 +230,23 @@ impl Lexer {
                     let ident = &self.input[start..end];
                     return match ident {
                         "let" => self.create_token(TokenType::Let, start, end),
+                        "const" => self.create_token(TokenType::Const, start, end),
                         "fn" => self.create_token(TokenType::Function, start, end),
                         "true" => self.create_token(TokenType::True, start, end),
                         "false" => self.create_token(TokenType::False, start, end),
                         "if" => self.create_token(TokenType::If, start, end),
                         "else" => self.create_token(TokenType::Else, start, end),
                         "return" => self.create_token(TokenType::Return, start, end),
+                        "break" => self.create_token(TokenType::Break, start, end),
+                        "continue" => self.create_token(TokenType::Continue, start, end),
+                        "struct" => self.create_token(TokenType::Struct, start, end),
+                        "enum" => self.create_token(TokenType::Enum, start, end),
+                        "import" => self.create_token(TokenType::Import, start, end),
+                        "export" => self.create_token(TokenType::Export, start, end),
                         "while" => self.create_token(TokenType::While, start, end),
+                        "match" => self.create_token(TokenType::Match, start, end),
+                        "for" => self.create_token(TokenType::For, start, end),
+                        "in" => self.create_token(TokenType::In, start, end),
                         _ => self.create_token(TokenType::Ident, start, end),
                     };
                 } else if Lexer::is_digit(self.ch) {
@@ -237,11 +302,12 @@ mod test {
         let lexer = Lexer::new(src);
         let expected_lexer = Lexer {
             input: String::from("let a;"),
+            file: 0,
             read_position: 1,
             position: 0,
             line: 1,
             col: 0,
-            ch: 'l' as u8,
+            ch: b'l',
         };
 
         assert_eq!(lexer, expected_lexer);
@@ -249,17 +315,17 @@ mod test {
 
     #[test]
     fn is_char() {
-        assert_eq!(Lexer::is_letter('a' as u8), true);
-        assert_eq!(Lexer::is_letter('_' as u8), true);
-        assert_eq!(Lexer::is_letter('1' as u8), false);
-        assert_eq!(Lexer::is_letter('[' as u8), false);
+        assert!(Lexer::is_letter(b'a'));
+        assert!(Lexer::is_letter(b'_'));
+        assert!(!Lexer::is_letter(b'1'));
+        assert!(!Lexer::is_letter(b'['));
     }
 
     #[test]
     fn is_digit() {
-        assert_eq!(Lexer::is_digit('1' as u8), true);
-        assert_eq!(Lexer::is_digit('2' as u8), true);
-        assert_eq!(Lexer::is_digit('a' as u8), false);
+        assert!(Lexer::is_digit(b'1'));
+        assert!(Lexer::is_digit(b'2'));
+        assert!(!Lexer::is_digit(b'a'));
     }
 
     #[test]
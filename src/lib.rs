@@ -1,8 +1,11 @@
 pub mod expression;
 pub mod identifier;
 pub mod lexer;
+pub mod module;
 pub mod parser;
+pub mod pattern;
 pub mod program;
+pub mod sourcemap;
 // pub mod repl;
 pub mod enviornment;
 pub mod interpreter;
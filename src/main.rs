@@ -1,20 +1,23 @@
 use newchai::enviornment::Enviornment;
 use newchai::interpreter::eval;
-use newchai::lexer::Lexer;
 use newchai::object::ChaiObject;
 use newchai::parser::Parser;
+use newchai::sourcemap::SourceMap;
 use newchai::stdchai::Std;
 
 fn main() -> Result<(), String> {
-    let src = std::fs::read_to_string("main.ch").map_err(|err| err.to_string())?;
-    let lexer = Lexer::new(src);
+    let path = std::env::args().nth(1).unwrap_or_else(|| "main.ch".into());
+    let mut sources = SourceMap::new();
+    let file = sources.load(&path)?;
+    let lexer = sources.lexer(file);
     // let mut lexer = Lexer::new(src);
     // println!("{:#?}", lexer.tokens());
     let mut parser = Parser::new(lexer);
     // println!("{:#?}", parser.parse_program()?);
+    let program = parser.parse_program().map_err(|err| err.render(&sources))?;
     let mut env = Enviornment::new();
-    let mut std = Std::load();
-    let object = eval(parser.parse_program()?, &mut env, &mut std);
+    let mut std = Std::with_sources(sources);
+    let object = eval(program, &mut env, &mut std);
     if let ChaiObject::Error(error) = object {
         return Err(error);
     }
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::object::Module;
+use crate::sourcemap::FileId;
+use crate::sourcemap::SourceMap;
+
+// Keeps track of every module imported while running a script, so each file
+// is only evaluated once and import cycles can be reported.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleLoader {
+    pub sources: SourceMap,
+    cache: HashMap<PathBuf, Module>,
+    loading: Vec<(PathBuf, String)>,
+}
+
+impl ModuleLoader {
+    pub fn new(sources: SourceMap) -> Self {
+        // Files loaded before the loader existed are the scripts being run,
+        // importing one of them again is a cycle.
+        let mut loading = Vec::new();
+        let mut file = 0;
+        while let Some(source) = sources.get(file) {
+            if let Ok(canonical) = std::fs::canonicalize(&source.path) {
+                loading.push((canonical, source.path.clone()));
+            }
+            file += 1;
+        }
+
+        ModuleLoader {
+            sources,
+            cache: HashMap::new(),
+            loading,
+        }
+    }
+
+    // Import paths are relative to the directory of the importing file
+    pub fn resolve(&self, from: FileId, path: &str) -> Result<(PathBuf, String), String> {
+        let directory = Path::new(self.sources.path(from))
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        let path = directory.join(path);
+        let canonical =
+            std::fs::canonicalize(&path).map_err(|err| format!("{} : {}", path.display(), err))?;
+        Ok((canonical, path.display().to_string()))
+    }
+
+    pub fn cached(&self, canonical: &Path) -> Option<Module> {
+        self.cache.get(canonical).cloned()
+    }
+
+    pub fn check_cycle(&self, canonical: &Path, path: &str) -> Result<(), String> {
+        let start = match self.loading.iter().position(|(file, _)| file == canonical) {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+
+        let mut cycle: Vec<&str> = self.loading[start..]
+            .iter()
+            .map(|(_, path)| path.as_str())
+            .collect();
+        cycle.push(path);
+        Err(format!("Import cycle : {}", cycle.join(" -> ")))
+    }
+
+    pub fn start(&mut self, canonical: PathBuf, path: String) {
+        self.loading.push((canonical, path));
+    }
+
+    pub fn finish(&mut self, canonical: PathBuf, module: Option<Module>) {
+        self.loading.retain(|(file, _)| *file != canonical);
+        if let Some(module) = module {
+            self.cache.insert(canonical, module);
+        }
+    }
+}
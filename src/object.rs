@@ -1,12 +1,129 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::rc::Rc;
 
+use crate::enviornment::Captured;
+use crate::expression::Parameter;
 use crate::statement::Statement;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Function(pub(crate) Vec<String>, pub(crate) Statement);
+pub struct Function {
+    pub(crate) name: Option<String>,
+    pub(crate) parameters: Vec<Parameter>,
+    pub(crate) body: Rc<Statement>,
+    // The scope the function was defined in
+    pub(crate) env: Captured,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "Function {}", name),
+            None => write!(f, "Function"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(i32),
+    Boolean(bool),
+    String(String),
+}
+
+impl HashKey {
+    pub fn from_object(object: &ChaiObject) -> Option<HashKey> {
+        match object {
+            ChaiObject::Integer(integer) => Some(HashKey::Integer(*integer)),
+            ChaiObject::Boolean(boolean) => Some(HashKey::Boolean(*boolean)),
+            ChaiObject::String(string) => Some(HashKey::String(string.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl From<HashKey> for ChaiObject {
+    fn from(key: HashKey) -> Self {
+        match key {
+            HashKey::Integer(integer) => ChaiObject::Integer(integer),
+            HashKey::Boolean(boolean) => ChaiObject::Boolean(boolean),
+            HashKey::String(string) => ChaiObject::String(string),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructType {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub(crate) name: String,
+    pub(crate) fields: Rc<Vec<(String, ChaiObject)>>,
+}
+
+impl Record {
+    pub fn get(&self, field: &str) -> Option<&ChaiObject> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        write!(f, "{} {{ {} }}", self.name, fields.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantType {
+    pub(crate) enum_name: String,
+    pub(crate) name: String,
+    pub(crate) fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumType {
+    pub(crate) name: String,
+    pub(crate) variants: Vec<VariantType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub(crate) enum_name: String,
+    pub(crate) name: String,
+    pub(crate) values: Rc<Vec<ChaiObject>>,
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.name)?;
+        if !self.values.is_empty() {
+            let values: Vec<String> = self.values.iter().map(|v| v.to_string()).collect();
+            write!(f, "({})", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub(crate) path: String,
+    pub(crate) exports: BTreeMap<String, ChaiObject>,
+}
 
 pub type BuildinFunction = fn(Vec<ChaiObject>) -> ChaiObject;
 
+#[allow(unpredictable_function_pointer_comparisons)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum ChaiObject {
     Integer(i32),
@@ -16,19 +133,78 @@ pub enum ChaiObject {
     Error(String),
     Function(Function),
     BuildinFunction(BuildinFunction),
-    Array(Vec<ChaiObject>),
+    // Shared until written to, so passing an array or hash around doesn't
+    // copy its elements
+    Array(Rc<Vec<ChaiObject>>),
+    Range(Option<i32>, Option<i32>),
+    Hash(Rc<BTreeMap<HashKey, ChaiObject>>),
+    Struct(StructType),
+    Record(Record),
+    Enum(EnumType),
+    VariantConstructor(VariantType),
+    Variant(Variant),
+    Module(Module),
     Print(String),
+    Break,
+    Continue,
     Null,
 }
 
+impl ChaiObject {
+    pub fn type_name(&self) -> String {
+        match self {
+            ChaiObject::Integer(_) => "Integer".into(),
+            ChaiObject::Boolean(_) => "Boolean".into(),
+            ChaiObject::String(_) => "String".into(),
+            ChaiObject::Array(_) => "Array".into(),
+            ChaiObject::Range(_, _) => "Range".into(),
+            ChaiObject::Hash(_) => "Hash".into(),
+            ChaiObject::Function(function) => function.to_string(),
+            ChaiObject::BuildinFunction(_) => "BuildinFunction".into(),
+            ChaiObject::Struct(_) => "Struct".into(),
+            ChaiObject::Record(record) => record.name.clone(),
+            ChaiObject::Enum(_) => "Enum".into(),
+            ChaiObject::VariantConstructor(variant) => {
+                format!("{}.{}", variant.enum_name, variant.name)
+            }
+            ChaiObject::Variant(variant) => format!("{}.{}", variant.enum_name, variant.name),
+            ChaiObject::Module(_) => "Module".into(),
+            ChaiObject::Null => "Null".into(),
+            _ => "Object".into(),
+        }
+    }
+}
+
 impl Display for ChaiObject {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ChaiObject::Integer(_) => write!(f, "Integer"),
-            ChaiObject::Boolean(_) => write!(f, "Boolean"),
-            ChaiObject::String(_) => write!(f, "String"),
-            ChaiObject::Null => write!(f, "Null"),
-            _ => write!(f, "{{ Object }}"),
+            ChaiObject::Integer(integer) => write!(f, "{}", integer),
+            ChaiObject::Boolean(boolean) => write!(f, "{}", boolean),
+            ChaiObject::String(string) => write!(f, "{}", string),
+            ChaiObject::Array(array) => {
+                let items: Vec<String> = array.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(","))
+            }
+            ChaiObject::Hash(hash) => {
+                let items: Vec<String> = hash
+                    .iter()
+                    .map(|(key, value)| format!("{}:{}", ChaiObject::from(key.clone()), value))
+                    .collect();
+                write!(f, "{{{}}}", items.join(","))
+            }
+            ChaiObject::Range(start, end) => {
+                let bound = |bound: &Option<i32>| bound.map(|b| b.to_string()).unwrap_or_default();
+                write!(f, "{}..{}", bound(start), bound(end))
+            }
+            ChaiObject::Struct(struct_type) => write!(f, "struct {}", struct_type.name),
+            ChaiObject::Record(record) => write!(f, "{}", record),
+            ChaiObject::Enum(enum_type) => write!(f, "enum {}", enum_type.name),
+            ChaiObject::Variant(variant) => write!(f, "{}", variant),
+            ChaiObject::Module(module) => write!(f, "Module {}", module.path),
+            ChaiObject::Return(value) => write!(f, "{}", value),
+            ChaiObject::Error(err) => write!(f, "Error : {}", err),
+            ChaiObject::Print(string) => write!(f, "{}", string),
+            _ => write!(f, "{}", self.type_name()),
         }
     }
 }
@@ -1,21 +1,43 @@
 use crate::expression::Expression;
+use crate::expression::MatchArm;
 use crate::expression::Operator;
+use crate::expression::Parameter;
 use crate::expression::Prefix;
 use crate::lexer::Lexer;
+use crate::pattern::Pattern;
+use crate::sourcemap::SourceMap;
+use crate::statement::ImportNames;
 use crate::statement::Statement;
 use crate::token::Token;
 use crate::token::TokenType;
+use crate::tokeninfo::TokenInfo;
 use std::collections::HashMap;
 
-type PrefixParseFn = fn(&mut Parser) -> Result<Expression, String>;
-type InfixParseFn = fn(&mut Parser, Expression) -> Result<Expression, String>;
+type PrefixParseFn = fn(&mut Parser) -> Result<Expression, ParseError>;
+type InfixParseFn = fn(&mut Parser, Expression) -> Result<Expression, ParseError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub token_info: TokenInfo,
+    pub message: String,
+}
+
+// Deliberately not Display, a parse error is only printed along with the
+// file it came from.
+impl ParseError {
+    pub fn render(&self, sources: &SourceMap) -> String {
+        format!("{} {}", sources.location(&self.token_info), self.message)
+    }
+}
 
 #[derive(Debug, PartialOrd, PartialEq)]
 pub enum Precedence {
     Lowest,
     Assign,
+    Pipeline,
     Equals,
     Lessgreater,
+    Range,
     Sum,
     Product,
     Prefix,
@@ -28,6 +50,8 @@ pub struct Parser {
     lexer: Lexer,
     current: Token,
     peek: Token,
+    loop_depth: usize,
+    block_depth: usize,
     prefix_fns: HashMap<TokenType, PrefixParseFn>,
     infix_fns: HashMap<TokenType, InfixParseFn>,
 }
@@ -38,6 +62,8 @@ impl Parser {
             lexer,
             current: Token::new(TokenType::Illegal),
             peek: Token::new(TokenType::Illegal),
+            loop_depth: 0,
+            block_depth: 0,
             prefix_fns: HashMap::new(),
             infix_fns: HashMap::new(),
         };
@@ -52,20 +78,41 @@ impl Parser {
         parser.register_prefix(TokenType::Lparen, Parser::parse_grouped_expression);
         parser.register_prefix(TokenType::If, Parser::parse_if_expression);
         parser.register_prefix(TokenType::While, Parser::parse_while_expression);
+        parser.register_prefix(TokenType::For, Parser::parse_for_expression);
+        parser.register_prefix(TokenType::Match, Parser::parse_match_expression);
         parser.register_prefix(TokenType::Function, Parser::parse_function_literal);
+        parser.register_prefix(TokenType::Bar, Parser::parse_lambda_expression);
         parser.register_prefix(TokenType::String, Parser::parse_string_literal);
         parser.register_prefix(TokenType::Lbracket, Parser::parse_array_literal);
+        parser.register_prefix(TokenType::Lbrace, Parser::parse_hash_literal);
+        parser.register_prefix(TokenType::Ellipsis, Parser::parse_spread_expression);
+        parser.register_prefix(TokenType::DotDot, Parser::parse_prefix_range_expression);
+        parser.register_prefix(
+            TokenType::DotDotEqual,
+            Parser::parse_prefix_range_expression,
+        );
         // Infix Expression Handlers
         parser.register_infix(TokenType::Plus, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Minus, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Asterisk, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Slash, Parser::parse_infix_expression);
+        parser.register_infix(TokenType::Percent, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Equal, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Notequal, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Greaterthan, Parser::parse_infix_expression);
         parser.register_infix(TokenType::Lessthan, Parser::parse_infix_expression);
-        parser.register_infix(TokenType::Assign, Parser::parse_infix_expression);
+        parser.register_infix(TokenType::Assign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::PlusAssign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::MinusAssign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::AsteriskAssign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::SlashAssign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::PercentAssign, Parser::parse_assign_expression);
+        parser.register_infix(TokenType::Pipeline, Parser::parse_pipeline_expression);
+        parser.register_infix(TokenType::DotDot, Parser::parse_range_expression);
+        parser.register_infix(TokenType::DotDotEqual, Parser::parse_range_expression);
         parser.register_infix(TokenType::Lparen, Parser::parse_call_expression);
+        parser.register_infix(TokenType::Dot, Parser::parse_member_expression);
+        parser.register_infix(TokenType::Lbrace, Parser::parse_struct_literal);
         parser.register_infix(TokenType::Lbracket, Parser::parse_array_index_expression);
         // Step parser
         parser.next_token();
@@ -116,20 +163,167 @@ impl Parser {
         TokenType::precedence(&self.current.token_type)
     }
 
-    fn parsing_error(&mut self, msg: &str) -> String {
-        format!(
-            "Line:{} Col:{} {}",
-            self.peek.token_info.line, self.peek.token_info.col, msg
-        )
+    fn parsing_error(&self, msg: &str) -> ParseError {
+        ParseError {
+            token_info: self.peek.token_info.clone(),
+            message: msg.into(),
+        }
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let literal = self.current.token_info.litertal.clone();
+        match self.current.token_type {
+            TokenType::Ident if literal == "_" => Ok(Pattern::Wildcard),
+            TokenType::Ident if self.peek_token_is(TokenType::Lparen) => {
+                self.parse_variant_pattern(literal)
+            }
+            TokenType::Ident if self.peek_token_is(TokenType::Dot) => {
+                self.next_token();
+                if !self.expect_peek_token(TokenType::Ident) {
+                    return Err(self.parsing_error("Expected a variant name"));
+                }
+                let name = format!("{}.{}", literal, self.current.token_info.litertal);
+                self.parse_variant_pattern(name)
+            }
+            TokenType::Ident => Ok(Pattern::Ident(literal)),
+            TokenType::Int => match self.parse_integer_literal()? {
+                Expression::Integer(integer) => Ok(Pattern::Integer(integer)),
+                _ => Err(self.parsing_error("Expected an Integer")),
+            },
+            TokenType::Minus if self.peek_token_is(TokenType::Int) => {
+                self.next_token();
+                match self.parse_integer_literal()? {
+                    Expression::Integer(integer) => Ok(Pattern::Integer(-integer)),
+                    _ => Err(self.parsing_error("Expected an Integer")),
+                }
+            }
+            TokenType::String => Ok(Pattern::String(literal)),
+            TokenType::True => Ok(Pattern::Boolean(true)),
+            TokenType::False => Ok(Pattern::Boolean(false)),
+            TokenType::Lbracket => self.parse_array_pattern(Parser::parse_pattern),
+            TokenType::Lbrace => self.parse_hash_pattern(),
+            _ => Err(self.parsing_error(&format!(
+                "Expected a pattern, found {}",
+                self.current.token_info.litertal
+            ))),
+        }
     }
 
-    fn parse_let_statement(&mut self) -> Result<Statement, String> {
-        let name = self.peek.token_info.litertal.clone();
+    // The patterns of `let` and parameters, which always match: names, `_`
+    // and arrays or hashes of them
+    fn parse_binding_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let literal = self.current.token_info.litertal.clone();
+        match self.current.token_type {
+            TokenType::Ident
+                if self.peek_token_is(TokenType::Lparen) || self.peek_token_is(TokenType::Dot) =>
+            {
+                Err(self.parsing_error("Variant patterns are only allowed in match arms"))
+            }
+            TokenType::Ident if literal == "_" => Ok(Pattern::Wildcard),
+            TokenType::Ident => Ok(Pattern::Ident(literal)),
+            TokenType::Lbracket => self.parse_array_pattern(Parser::parse_binding_pattern),
+            TokenType::Lbrace => self.parse_hash_pattern(),
+            _ => Err(self.parsing_error(&format!(
+                "Literal patterns are only allowed in match arms, found {}",
+                literal
+            ))),
+        }
+    }
 
-        if !self.expect_peek_token(TokenType::Ident) {
+    fn check_duplicate_names(&self, pattern: &Pattern) -> Result<(), ParseError> {
+        let names = pattern.names();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(self.parsing_error(&format!("Duplicate name {} in pattern", name)));
+            }
+        }
+        Ok(())
+    }
+
+    // `Circle(r)` or `Shape.Circle(r)`, the payload is optional for the
+    // qualified form so unit variants can be matched as `Shape.Empty`
+    fn parse_variant_pattern(&mut self, name: String) -> Result<Pattern, ParseError> {
+        let mut values = Vec::new();
+        if !self.expect_peek_token(TokenType::Lparen) {
+            return Ok(Pattern::Variant(name, values));
+        }
+
+        while !self.peek_token_is(TokenType::Rparen) {
+            self.next_token();
+            values.push(self.parse_pattern()?);
+
+            if !self.peek_token_is(TokenType::Rparen) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or )"));
+            }
+        }
+
+        self.next_token();
+        Ok(Pattern::Variant(name, values))
+    }
+
+    fn parse_array_pattern(
+        &mut self,
+        element: fn(&mut Parser) -> Result<Pattern, ParseError>,
+    ) -> Result<Pattern, ParseError> {
+        let mut elements = Vec::new();
+        let mut rest = None;
+
+        while !self.peek_token_is(TokenType::Rbracket) {
+            if self.expect_peek_token(TokenType::Ellipsis) {
+                if !self.expect_peek_token(TokenType::Ident) {
+                    return Err(self.parsing_error("Expected an Identifier"));
+                }
+                rest = Some(self.current.token_info.litertal.clone());
+                break;
+            }
+
+            self.next_token();
+            elements.push(element(self)?);
+
+            if !self.peek_token_is(TokenType::Rbracket) && !self.expect_peek_token(TokenType::Comma)
+            {
+                return Err(self.parsing_error("Expected , or ]"));
+            }
+        }
+
+        if !self.expect_peek_token(TokenType::Rbracket) {
+            return Err(self.parsing_error("Expected ]"));
+        }
+
+        Ok(Pattern::Array(elements, rest))
+    }
+
+    fn parse_hash_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let mut keys = Vec::new();
+
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected an Identifier"));
+            }
+            keys.push(self.current.token_info.litertal.clone());
+
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+        Ok(Pattern::Hash(keys))
+    }
+
+    fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
+        let constant = self.current_token_is(TokenType::Const);
+        if !self.peek_token_is(TokenType::Ident)
+            && !self.peek_token_is(TokenType::Lbracket)
+            && !self.peek_token_is(TokenType::Lbrace)
+        {
             return Err(self.parsing_error("Expected an Identifier"));
         }
 
+        self.next_token();
+        let pattern = self.parse_binding_pattern()?;
+        self.check_duplicate_names(&pattern)?;
+
         if !self.expect_peek_token(TokenType::Assign) {
             return Err(self.parsing_error("Expected ="));
         }
@@ -138,7 +332,11 @@ impl Parser {
 
         let expression = self.parse_expression(Precedence::Lowest)?;
 
-        let let_statement = Statement::Let(name, expression.into());
+        let let_statement = if constant {
+            Statement::Const(pattern, expression.into())
+        } else {
+            Statement::Let(pattern, expression.into())
+        };
 
         if self.peek_token_is(TokenType::Semicolon) {
             self.next_token();
@@ -147,7 +345,7 @@ impl Parser {
         Ok(let_statement)
     }
 
-    fn parse_return_statement(&mut self) -> Result<Statement, String> {
+    fn parse_return_statement(&mut self) -> Result<Statement, ParseError> {
         self.next_token();
 
         let expression = self.parse_expression(Precedence::Lowest)?;
@@ -161,25 +359,52 @@ impl Parser {
         Ok(return_statement)
     }
 
-    fn parse_identifier(&mut self) -> Result<Expression, String> {
+    fn parse_loop_control_statement(&mut self) -> Result<Statement, ParseError> {
+        let statement = match self.current.token_type {
+            TokenType::Break => Statement::Break,
+            _ => Statement::Continue,
+        };
+
+        if self.loop_depth == 0 {
+            return Err(self.parsing_error(&format!(
+                "{} outside of a loop",
+                self.current.token_info.litertal
+            )));
+        }
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(statement)
+    }
+
+    fn parse_loop_body(&mut self) -> Result<Statement, ParseError> {
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_identifier(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::Ident(self.current.token_info.litertal.clone()))
     }
 
-    fn parse_integer_literal(&mut self) -> Result<Expression, String> {
+    fn parse_integer_literal(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::Integer(
             self.current
                 .token_info
                 .litertal
                 .parse::<i32>()
-                .map_err(|err| err.to_string())?,
+                .map_err(|err| self.parsing_error(&err.to_string()))?,
         ))
     }
 
-    fn parse_boolean_literal(&mut self) -> Result<Expression, String> {
+    fn parse_boolean_literal(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::Boolean(self.current_token_is(TokenType::True)))
     }
 
-    fn parse_grouped_expression(&mut self) -> Result<Expression, String> {
+    fn parse_grouped_expression(&mut self) -> Result<Expression, ParseError> {
         self.next_token();
 
         let grouped_expression = self.parse_expression(Precedence::Lowest)?;
@@ -191,7 +416,7 @@ impl Parser {
         Ok(grouped_expression)
     }
 
-    fn parse_if_expression(&mut self) -> Result<Expression, String> {
+    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
         if !self.expect_peek_token(TokenType::Lparen) {
             return Err(self.parsing_error("Expected a ("));
         }
@@ -212,6 +437,18 @@ impl Parser {
 
         if self.peek_token_is(TokenType::Else) {
             self.next_token();
+
+            if self.expect_peek_token(TokenType::If) {
+                let else_if = self.parse_if_expression()?;
+                let else_statement =
+                    Statement::BlockStatement(vec![Statement::ExpressionStatement(else_if.into())]);
+                return Ok(Expression::If(
+                    condition.into(),
+                    then_statement,
+                    Some(else_statement),
+                ));
+            }
+
             if !self.expect_peek_token(TokenType::Lbrace) {
                 return Err(self.parsing_error("Expected a {"));
             }
@@ -228,7 +465,7 @@ impl Parser {
         Ok(Expression::If(condition.into(), then_statement, None))
     }
 
-    fn parse_while_expression(&mut self) -> Result<Expression, String> {
+    fn parse_while_expression(&mut self) -> Result<Expression, ParseError> {
         if !self.expect_peek_token(TokenType::Lparen) {
             return Err(self.parsing_error("Expected a ("));
         }
@@ -245,54 +482,444 @@ impl Parser {
             return Err(self.parsing_error("Expected a {"));
         }
 
-        let while_statement = self.parse_block_statement()?;
+        let while_statement = self.parse_loop_body()?;
 
         Ok(Expression::While(condition.into(), while_statement))
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<String>, String> {
+    fn parse_for_expression(&mut self) -> Result<Expression, ParseError> {
+        if !self.expect_peek_token(TokenType::Lparen) {
+            return Err(self.parsing_error("Expected a ("));
+        }
+
+        if !self.expect_peek_token(TokenType::Ident) {
+            return Err(self.parsing_error("Expected an Identifier"));
+        }
+
+        let mut index = None;
+        let mut item = self.current.token_info.litertal.clone();
+
+        if self.expect_peek_token(TokenType::Comma) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected an Identifier"));
+            }
+            index = Some(item);
+            item = self.current.token_info.litertal.clone();
+        }
+
+        if !self.expect_peek_token(TokenType::In) {
+            return Err(self.parsing_error("Expected in"));
+        }
+
+        self.next_token();
+
+        let collection = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek_token(TokenType::Rparen) {
+            return Err(self.parsing_error("Expected a )"));
+        }
+
+        if !self.expect_peek_token(TokenType::Lbrace) {
+            return Err(self.parsing_error("Expected a {"));
+        }
+
+        let body = self.parse_loop_body()?;
+
+        Ok(Expression::For(index, item, collection.into(), body))
+    }
+
+    fn parse_match_arm(&mut self) -> Result<MatchArm, ParseError> {
+        let mut pattern = self.parse_pattern()?;
+
+        if self.peek_token_is(TokenType::Bar) {
+            let mut alternatives = vec![pattern];
+            while self.expect_peek_token(TokenType::Bar) {
+                self.next_token();
+                alternatives.push(self.parse_pattern()?);
+            }
+
+            // The body can't tell which alternative matched, so each has to
+            // bind the same names
+            let sorted_names = |pattern: &Pattern| {
+                let mut names = pattern.names();
+                names.sort();
+                names
+            };
+            let names = sorted_names(&alternatives[0]);
+            if alternatives[1..].iter().any(|p| sorted_names(p) != names) {
+                return Err(self.parsing_error("Alternatives must bind the same names"));
+            }
+            pattern = Pattern::Alternative(alternatives);
+        }
+        if let Pattern::Alternative(alternatives) = &pattern {
+            for alternative in alternatives {
+                self.check_duplicate_names(alternative)?;
+            }
+        } else {
+            self.check_duplicate_names(&pattern)?;
+        }
+
+        let mut guard = None;
+        if self.expect_peek_token(TokenType::If) {
+            self.next_token();
+            guard = Some(self.parse_expression(Precedence::Lowest)?);
+        }
+
+        if !self.expect_peek_token(TokenType::FatArrow) {
+            return Err(self.parsing_error("Expected =>"));
+        }
+
+        let body = if self.expect_peek_token(TokenType::Lbrace) {
+            self.parse_block_statement()?
+        } else {
+            self.next_token();
+            let expression = self.parse_expression(Precedence::Lowest)?;
+            Statement::ExpressionStatement(expression.into())
+        };
+
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body,
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression, ParseError> {
+        if !self.expect_peek_token(TokenType::Lparen) {
+            return Err(self.parsing_error("Expected a ("));
+        }
+
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek_token(TokenType::Rparen) {
+            return Err(self.parsing_error("Expected a )"));
+        }
+
+        if !self.expect_peek_token(TokenType::Lbrace) {
+            return Err(self.parsing_error("Expected a {"));
+        }
+
+        let mut arms = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if self.peek_token_is(TokenType::Eof) {
+                return Err(self.parsing_error("Expected }"));
+            }
+            self.next_token();
+            arms.push(self.parse_match_arm()?);
+
+            if !self.peek_token_is(TokenType::Rbrace)
+                && !self.expect_peek_token(TokenType::Comma)
+                && !self.current_token_is(TokenType::Rbrace)
+            {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+        Ok(Expression::Match(value.into(), arms))
+    }
+
+    fn parse_function_parameter(&mut self) -> Result<Parameter, ParseError> {
+        if self.current_token_is(TokenType::Ellipsis) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected an Identifier"));
+            }
+            return Ok(Parameter {
+                pattern: Pattern::Ident(self.current.token_info.litertal.clone()),
+                default: None,
+                rest: true,
+            });
+        }
+
+        let pattern = match self.current.token_type {
+            TokenType::Ident | TokenType::Lbracket | TokenType::Lbrace => {
+                self.parse_binding_pattern()?
+            }
+            _ => {
+                return Err(self.parsing_error(&format!(
+                    "Expected a parameter name, found {}",
+                    self.current.token_info.litertal
+                )))
+            }
+        };
+
+        let mut default = None;
+        if self.expect_peek_token(TokenType::Assign) {
+            self.next_token();
+            default = Some(self.parse_expression(Precedence::Lowest)?);
+        }
+
+        Ok(Parameter {
+            pattern,
+            default,
+            rest: false,
+        })
+    }
+
+    fn parse_function_parameters(
+        &mut self,
+        closing: TokenType,
+    ) -> Result<Vec<Parameter>, ParseError> {
         let mut parameters = Vec::new();
-        if self.peek_token_is(TokenType::Rparen) {
+        if self.peek_token_is(closing.clone()) {
             self.next_token();
             return Ok(parameters);
         }
         self.next_token();
-        parameters.push(self.current.token_info.litertal.clone());
+        parameters.push(self.parse_function_parameter()?);
 
         while self.peek_token_is(TokenType::Comma) {
+            if parameters.iter().any(|parameter| parameter.rest) {
+                return Err(self.parsing_error("Rest parameter must be the last parameter"));
+            }
             self.next_token();
             self.next_token();
-            parameters.push(self.current.token_info.litertal.clone());
+            parameters.push(self.parse_function_parameter()?);
         }
 
-        if !self.expect_peek_token(TokenType::Rparen) {
-            return Err(self.parsing_error("Expected a )"));
+        let mut names = Vec::new();
+        for parameter in &parameters {
+            for name in parameter.pattern.names() {
+                if names.contains(&name) {
+                    return Err(self.parsing_error(&format!("Duplicate parameter name {}", name)));
+                }
+                names.push(name);
+            }
+        }
+
+        if !self.expect_peek_token(closing.clone()) {
+            let closing = if closing == TokenType::Bar { "|" } else { ")" };
+            return Err(self.parsing_error(&format!("Expected a {}", closing)));
         }
 
         Ok(parameters)
     }
 
-    fn parse_function_literal(&mut self) -> Result<Expression, String> {
+    fn parse_function_definition(&mut self) -> Result<(Vec<Parameter>, Statement), ParseError> {
         if !self.expect_peek_token(TokenType::Lparen) {
             return Err(self.parsing_error("Expected a ("));
         }
 
-        let parameter = self.parse_function_parameters()?;
+        let parameter = self.parse_function_parameters(TokenType::Rparen)?;
 
         if !self.expect_peek_token(TokenType::Lbrace) {
             return Err(self.parsing_error("Expected a {"));
         }
 
-        let body = self.parse_block_statement()?;
+        // Loops around a function don't extend into its body
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+
+        Ok((parameter, body?))
+    }
 
-        Ok(Expression::Function(parameter, body))
+    fn parse_function_literal(&mut self) -> Result<Expression, ParseError> {
+        let (parameter, body) = self.parse_function_definition()?;
+        Ok(Expression::Function(parameter, body.into()))
     }
 
-    fn parse_string_literal(&mut self) -> Result<Expression, String> {
+    // `|x| x * 2` is `fn(x) { return x * 2; }`, a `{` after the parameters
+    // starts a regular function body instead
+    fn parse_lambda_expression(&mut self) -> Result<Expression, ParseError> {
+        let parameters = self.parse_function_parameters(TokenType::Bar)?;
+
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = if self.expect_peek_token(TokenType::Lbrace) {
+            self.parse_block_statement()
+        } else {
+            self.next_token();
+            self.parse_expression(Precedence::Lowest)
+                .map(|body| Statement::BlockStatement(vec![Statement::Return(body.into())]))
+        };
+        self.loop_depth = loop_depth;
+
+        Ok(Expression::Function(parameters, body?.into()))
+    }
+
+    fn parse_struct_statement(&mut self) -> Result<Statement, ParseError> {
+        if !self.expect_peek_token(TokenType::Ident) {
+            return Err(self.parsing_error("Expected an Identifier"));
+        }
+        let name = self.current.token_info.litertal.clone();
+
+        if !self.expect_peek_token(TokenType::Lbrace) {
+            return Err(self.parsing_error("Expected a {"));
+        }
+
+        let mut fields: Vec<String> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected a field name"));
+            }
+            let field = self.current.token_info.litertal.clone();
+            if fields.contains(&field) {
+                return Err(self.parsing_error(&format!("Duplicate field name {}", field)));
+            }
+            fields.push(field);
+
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Struct(name, fields))
+    }
+
+    // `enum Shape { Circle(r), Rect(w, h), Empty }`
+    fn parse_enum_statement(&mut self) -> Result<Statement, ParseError> {
+        if !self.expect_peek_token(TokenType::Ident) {
+            return Err(self.parsing_error("Expected an Identifier"));
+        }
+        let name = self.current.token_info.litertal.clone();
+
+        if !self.expect_peek_token(TokenType::Lbrace) {
+            return Err(self.parsing_error("Expected a {"));
+        }
+
+        let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected a variant name"));
+            }
+            let variant = self.current.token_info.litertal.clone();
+            if variants.iter().any(|(name, _)| *name == variant) {
+                return Err(self.parsing_error(&format!("Duplicate variant name {}", variant)));
+            }
+
+            let mut fields: Vec<String> = Vec::new();
+            if self.expect_peek_token(TokenType::Lparen) {
+                while !self.peek_token_is(TokenType::Rparen) {
+                    if !self.expect_peek_token(TokenType::Ident) {
+                        return Err(self.parsing_error("Expected a field name"));
+                    }
+                    let field = self.current.token_info.litertal.clone();
+                    if fields.contains(&field) {
+                        return Err(self.parsing_error(&format!("Duplicate field name {}", field)));
+                    }
+                    fields.push(field);
+
+                    if !self.peek_token_is(TokenType::Rparen)
+                        && !self.expect_peek_token(TokenType::Comma)
+                    {
+                        return Err(self.parsing_error("Expected , or )"));
+                    }
+                }
+                self.next_token();
+            }
+            variants.push((variant, fields));
+
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Enum(name, variants))
+    }
+
+    fn parse_import_statement(&mut self) -> Result<Statement, ParseError> {
+        let file = self.current.token_info.file;
+
+        let names = if self.expect_peek_token(TokenType::Lbrace) {
+            let mut names: Vec<String> = Vec::new();
+            while !self.peek_token_is(TokenType::Rbrace) {
+                if !self.expect_peek_token(TokenType::Ident) {
+                    return Err(self.parsing_error("Expected an Identifier"));
+                }
+                names.push(self.current.token_info.litertal.clone());
+
+                if !self.peek_token_is(TokenType::Rbrace)
+                    && !self.expect_peek_token(TokenType::Comma)
+                {
+                    return Err(self.parsing_error("Expected , or }"));
+                }
+            }
+            self.next_token();
+
+            if !self.peek_token_is(TokenType::Ident) || self.peek.token_info.litertal != "from" {
+                return Err(self.parsing_error("Expected from"));
+            }
+            self.next_token();
+            Some(names)
+        } else {
+            None
+        };
+
+        if !self.expect_peek_token(TokenType::String) {
+            return Err(self.parsing_error("Expected a module path"));
+        }
+        let path = self.current.token_info.litertal.clone();
+
+        let names = match names {
+            Some(names) => ImportNames::Names(names),
+            None => {
+                if !self.peek_token_is(TokenType::Ident) || self.peek.token_info.litertal != "as" {
+                    return Err(self.parsing_error("Expected as"));
+                }
+                self.next_token();
+                if !self.expect_peek_token(TokenType::Ident) {
+                    return Err(self.parsing_error("Expected an Identifier"));
+                }
+                ImportNames::Module(self.current.token_info.litertal.clone())
+            }
+        };
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Import(path, names, file))
+    }
+
+    fn parse_export_statement(&mut self) -> Result<Statement, ParseError> {
+        if self.block_depth > 0 {
+            return Err(self.parsing_error("export is only allowed at the top level"));
+        }
+
+        self.next_token();
+        let statement = self.parse_statement()?;
+        match statement {
+            Statement::Let(_, _)
+            | Statement::Const(_, _)
+            | Statement::Function(_, _, _)
+            | Statement::Struct(_, _)
+            | Statement::Enum(_, _) => Ok(Statement::Export(statement.into())),
+            _ => Err(self.parsing_error("Only declarations can be exported")),
+        }
+    }
+
+    fn parse_function_statement(&mut self) -> Result<Statement, ParseError> {
+        self.next_token();
+        let name = self.current.token_info.litertal.clone();
+
+        let (parameter, body) = self.parse_function_definition()?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(Statement::Function(name, parameter, body.into()))
+    }
+
+    fn parse_string_literal(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::String(self.current.token_info.litertal.clone()))
     }
 
-    fn parse_expression_array(&mut self) -> Result<Vec<Expression>, String> {
+    fn parse_expression_array(&mut self) -> Result<Vec<Expression>, ParseError> {
         let mut array = Vec::new();
         if self.peek_token_is(TokenType::Rbracket) {
             self.next_token();
@@ -314,22 +941,89 @@ impl Parser {
         Ok(array)
     }
 
-    fn parse_array_literal(&mut self) -> Result<Expression, String> {
+    fn parse_array_literal(&mut self) -> Result<Expression, ParseError> {
         let array_elements = self.parse_expression_array()?;
         Ok(Expression::Array(array_elements))
     }
 
-    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_hash_literal(&mut self) -> Result<Expression, ParseError> {
+        let mut pairs = Vec::new();
+
+        while !self.peek_token_is(TokenType::Rbrace) {
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+
+            if !self.expect_peek_token(TokenType::Colon) {
+                return Err(self.parsing_error("Expected :"));
+            }
+
+            self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+        Ok(Expression::Hash(pairs))
+    }
+
+    fn parse_spread_expression(&mut self) -> Result<Expression, ParseError> {
+        self.next_token();
+        let expression = self.parse_expression(Precedence::Lowest)?;
+        Ok(Expression::Spread(expression.into()))
+    }
+
+    // The end of a range is optional when nothing follows it, eg. `arr[2..]`
+    fn parse_range_end(&mut self) -> Result<Option<Box<Expression>>, ParseError> {
+        let inclusive = self.current_token_is(TokenType::DotDotEqual);
+        let has_end = !matches!(
+            self.peek.token_type,
+            TokenType::Rbracket
+                | TokenType::Rparen
+                | TokenType::Rbrace
+                | TokenType::Comma
+                | TokenType::Semicolon
+                | TokenType::Eof
+        );
+
+        if !has_end {
+            if inclusive {
+                return Err(self.parsing_error("Expected the end of an inclusive range"));
+            }
+            return Ok(None);
+        }
+
+        self.next_token();
+        let end = self.parse_expression(Precedence::Range)?;
+        Ok(Some(end.into()))
+    }
+
+    fn parse_range_expression(&mut self, start: Expression) -> Result<Expression, ParseError> {
+        let inclusive = self.current_token_is(TokenType::DotDotEqual);
+        let end = self.parse_range_end()?;
+        Ok(Expression::Range(Some(start.into()), end, inclusive))
+    }
+
+    fn parse_prefix_range_expression(&mut self) -> Result<Expression, ParseError> {
+        let inclusive = self.current_token_is(TokenType::DotDotEqual);
+        let end = self.parse_range_end()?;
+        Ok(Expression::Range(None, end, inclusive))
+    }
+
+    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
         let operator = match self.current.token_type {
             TokenType::Plus => Operator::Plus,
             TokenType::Minus => Operator::Minus,
             TokenType::Asterisk => Operator::Multiply,
             TokenType::Slash => Operator::Divide,
+            TokenType::Percent => Operator::Modulo,
             TokenType::Equal => Operator::Equals,
             TokenType::Notequal => Operator::Notequals,
             TokenType::Lessthan => Operator::Lessthan,
             TokenType::Greaterthan => Operator::Greaterthan,
-            TokenType::Assign => Operator::Assign,
             _ => return Err(self.parsing_error("Not an infix expression")),
         };
 
@@ -340,8 +1034,66 @@ impl Parser {
         Ok(infix_expression)
     }
 
-    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, String> {
-        let mut arguments = Vec::new();
+    fn is_assignable(expression: &Expression) -> bool {
+        match expression {
+            Expression::Ident(_) => true,
+            Expression::ArrayIndex(container, _) => Parser::is_assignable(container),
+            Expression::Member(container, _) => Parser::is_assignable(container),
+            _ => false,
+        }
+    }
+
+    fn parse_assign_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
+        if !Parser::is_assignable(&left) {
+            return Err(self.parsing_error("Invalid assignment target"));
+        }
+
+        let operator = match self.current.token_type {
+            TokenType::PlusAssign => Some(Operator::Plus),
+            TokenType::MinusAssign => Some(Operator::Minus),
+            TokenType::AsteriskAssign => Some(Operator::Multiply),
+            TokenType::SlashAssign => Some(Operator::Divide),
+            TokenType::PercentAssign => Some(Operator::Modulo),
+            _ => None,
+        };
+
+        // Parsing the right side at the lowest precedence makes assignment
+        // right associative, `a = b = 0` is `a = (b = 0)`
+        self.next_token();
+        let right = self.parse_expression(Precedence::Lowest)?;
+
+        match operator {
+            Some(operator) => Ok(Expression::CompoundAssign(
+                left.into(),
+                operator,
+                right.into(),
+            )),
+            None => Ok(Expression::Infix(
+                left.into(),
+                Operator::Assign,
+                right.into(),
+            )),
+        }
+    }
+
+    fn parse_call_argument(&mut self, named_seen: bool) -> Result<Expression, ParseError> {
+        if self.current_token_is(TokenType::Ident) && self.peek_token_is(TokenType::Colon) {
+            let name = self.current.token_info.litertal.clone();
+            self.next_token();
+            self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            return Ok(Expression::NamedArgument(name, value.into()));
+        }
+
+        if named_seen {
+            return Err(self.parsing_error("Positional argument after named argument"));
+        }
+
+        self.parse_expression(Precedence::Lowest)
+    }
+
+    fn parse_call_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut arguments: Vec<Expression> = Vec::new();
 
         if self.peek_token_is(TokenType::Rparen) {
             self.next_token();
@@ -350,12 +1102,13 @@ impl Parser {
 
         self.next_token();
 
-        arguments.push(self.parse_expression(Precedence::Lowest)?);
+        arguments.push(self.parse_call_argument(false)?);
 
         while self.peek_token_is(TokenType::Comma) {
             self.next_token();
             self.next_token();
-            arguments.push(self.parse_expression(Precedence::Lowest)?);
+            let named_seen = matches!(arguments.last(), Some(Expression::NamedArgument(_, _)));
+            arguments.push(self.parse_call_argument(named_seen)?);
         }
 
         if !self.expect_peek_token(TokenType::Rparen) {
@@ -365,13 +1118,68 @@ impl Parser {
         Ok(arguments)
     }
 
-    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, String> {
+    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, ParseError> {
         let arguments = self.parse_call_arguments()?;
         let call_expression = Expression::FunctionCall(function.into(), arguments);
         Ok(call_expression)
     }
 
-    fn parse_array_index_expression(&mut self, array: Expression) -> Result<Expression, String> {
+    // `Point { x: 1, y: 2 }`
+    fn parse_struct_literal(&mut self, name: Expression) -> Result<Expression, ParseError> {
+        let name = match name {
+            Expression::Ident(name) => name,
+            _ => return Err(self.parsing_error("Expected a struct name before {")),
+        };
+
+        let mut fields = Vec::new();
+        while !self.peek_token_is(TokenType::Rbrace) {
+            if !self.expect_peek_token(TokenType::Ident) {
+                return Err(self.parsing_error("Expected a field name"));
+            }
+            let field = self.current.token_info.litertal.clone();
+
+            if !self.expect_peek_token(TokenType::Colon) {
+                return Err(self.parsing_error("Expected :"));
+            }
+
+            self.next_token();
+            fields.push((field, self.parse_expression(Precedence::Lowest)?));
+
+            if !self.peek_token_is(TokenType::Rbrace) && !self.expect_peek_token(TokenType::Comma) {
+                return Err(self.parsing_error("Expected , or }"));
+            }
+        }
+
+        self.next_token();
+        Ok(Expression::StructLiteral(name, fields))
+    }
+
+    fn parse_member_expression(&mut self, object: Expression) -> Result<Expression, ParseError> {
+        if !self.expect_peek_token(TokenType::Ident) {
+            return Err(self.parsing_error("Expected an Identifier"));
+        }
+        let name = self.current.token_info.litertal.clone();
+        Ok(Expression::Member(object.into(), name))
+    }
+
+    // `x |> f(y)` is `f(x, y)` and `x |> f` is `f(x)`
+    fn parse_pipeline_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
+        let precedence = self.current_precedence();
+        self.next_token();
+        let right = self.parse_expression(precedence)?;
+        match right {
+            Expression::FunctionCall(function, mut arguments) => {
+                arguments.insert(0, left);
+                Ok(Expression::FunctionCall(function, arguments))
+            }
+            function => Ok(Expression::FunctionCall(function.into(), vec![left])),
+        }
+    }
+
+    fn parse_array_index_expression(
+        &mut self,
+        array: Expression,
+    ) -> Result<Expression, ParseError> {
         self.next_token();
         let index = self.parse_expression(Precedence::Lowest)?;
         if !self.expect_peek_token(TokenType::Rbracket) {
@@ -380,7 +1188,7 @@ impl Parser {
         Ok(Expression::ArrayIndex(array.into(), index.into()))
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, String> {
+    fn parse_prefix_expression(&mut self) -> Result<Expression, ParseError> {
         let prefix = match self.current.token_type {
             TokenType::Minus => Prefix::Minus,
             TokenType::Bang => Prefix::Bang,
@@ -392,7 +1200,7 @@ impl Parser {
         Ok(Expression::Prefix(prefix, expression.into()))
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
         let prefix = self.prefix_fns.get(&self.current.token_type);
 
         if prefix.is_none() {
@@ -420,7 +1228,7 @@ impl Parser {
         Ok(left_expr)
     }
 
-    fn parse_expression_statement(&mut self) -> Result<Statement, String> {
+    fn parse_expression_statement(&mut self) -> Result<Statement, ParseError> {
         let expression = self.parse_expression(Precedence::Lowest)?;
 
         let expression_statement = Statement::ExpressionStatement(expression.into());
@@ -432,26 +1240,36 @@ impl Parser {
         Ok(expression_statement)
     }
 
-    fn parse_block_statement(&mut self) -> Result<Statement, String> {
+    fn parse_block_statement(&mut self) -> Result<Statement, ParseError> {
         let mut statements = Vec::new();
         self.next_token();
+        self.block_depth += 1;
         while !self.current_token_is(TokenType::Rbrace) && !self.current_token_is(TokenType::Eof) {
             let statement = self.parse_statement()?;
             statements.push(statement);
             self.next_token();
         }
+        self.block_depth -= 1;
         Ok(Statement::BlockStatement(statements))
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, String> {
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         match self.current.token_type {
-            TokenType::Let => self.parse_let_statement(),
+            TokenType::Let | TokenType::Const => self.parse_let_statement(),
             TokenType::Return => self.parse_return_statement(),
+            TokenType::Break | TokenType::Continue => self.parse_loop_control_statement(),
+            TokenType::Struct => self.parse_struct_statement(),
+            TokenType::Enum => self.parse_enum_statement(),
+            TokenType::Import => self.parse_import_statement(),
+            TokenType::Export => self.parse_export_statement(),
+            TokenType::Function if self.peek_token_is(TokenType::Ident) => {
+                self.parse_function_statement()
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
-    pub fn parse_program(&mut self) -> Result<Statement, String> {
+    pub fn parse_program(&mut self) -> Result<Statement, ParseError> {
         let mut program = Vec::new();
         while self.current.token_type != TokenType::Eof {
             let statement = self.parse_statement()?;
@@ -461,3 +1279,127 @@ impl Parser {
         Ok(Statement::Program(program))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_error_renders_file_location() {
+        let mut sources = SourceMap::new();
+        sources.add("main.ch", "let a = 1;".into());
+        let file = sources.add("lib/utils.ch", "let a = 1;\nlet = 2;".into());
+        let mut parser = Parser::new(sources.lexer(file));
+        let err = parser.parse_program().unwrap_err();
+        assert_eq!(err.token_info.file, file);
+        assert_eq!(
+            err.render(&sources),
+            "lib/utils.ch:2:5 Expected an Identifier"
+        );
+    }
+
+    #[test]
+    fn else_if_parses_as_nested_if() {
+        let chained = "if (a) { 1 } else if (b) { 2 } else { 3 }";
+        let nested = "if (a) { 1 } else { if (b) { 2 } else { 3 } }";
+        let chained = Parser::new(Lexer::new(chained.into())).parse_program();
+        let nested = Parser::new(Lexer::new(nested.into())).parse_program();
+        assert_eq!(chained, nested);
+    }
+
+    #[test]
+    fn break_outside_loop_is_an_error() {
+        let src = "while (true) { let f = fn() { break; }; }";
+        let err = Parser::new(Lexer::new(src.into()))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(err.message, "break outside of a loop");
+
+        let src = "continue;";
+        let err = Parser::new(Lexer::new(src.into()))
+            .parse_program()
+            .unwrap_err();
+        assert_eq!(err.message, "continue outside of a loop");
+    }
+
+    #[test]
+    fn invalid_assignment_target_is_an_error() {
+        for src in ["1 = 2;", "f() = 2;", "a[0] + 1 = 2;"] {
+            let err = Parser::new(Lexer::new(src.into()))
+                .parse_program()
+                .unwrap_err();
+            assert_eq!(err.message, "Invalid assignment target");
+        }
+    }
+
+    #[test]
+    fn invalid_function_parameters() {
+        let cases = [
+            ("fn(a, 1) { }", "Expected a parameter name, found 1"),
+            ("fn(a, [b, a]) { }", "Duplicate parameter name a"),
+            (
+                "fn(a.b) { }",
+                "Variant patterns are only allowed in match arms",
+            ),
+            (
+                "fn(a(b)) { }",
+                "Variant patterns are only allowed in match arms",
+            ),
+            (
+                "fn([1, x]) { }",
+                "Literal patterns are only allowed in match arms, found 1",
+            ),
+            (
+                "let Foo(x) = 1;",
+                "Variant patterns are only allowed in match arms",
+            ),
+            (
+                "let [1, x] = [1, 2];",
+                "Literal patterns are only allowed in match arms, found 1",
+            ),
+            ("let [a, a] = [1, 2];", "Duplicate name a in pattern"),
+            ("let {a, b, a} = h;", "Duplicate name a in pattern"),
+            ("f(a: 1, 2);", "Positional argument after named argument"),
+            (
+                "fn(...a, b) { }",
+                "Rest parameter must be the last parameter",
+            ),
+        ];
+        for (src, message) in cases {
+            let err = Parser::new(Lexer::new(src.into()))
+                .parse_program()
+                .unwrap_err();
+            assert_eq!(err.message, message);
+        }
+    }
+
+    #[test]
+    fn invalid_match_patterns() {
+        let cases = [
+            (
+                "match (5) { [a] | b => a }",
+                "Alternatives must bind the same names",
+            ),
+            (
+                "match (5) { 1 | a => a }",
+                "Alternatives must bind the same names",
+            ),
+            ("match (5) { [a, a] => a }", "Duplicate name a in pattern"),
+        ];
+        for (src, message) in cases {
+            let err = Parser::new(Lexer::new(src.into()))
+                .parse_program()
+                .unwrap_err();
+            assert_eq!(err.message, message);
+        }
+        let src = "match (5) { [a, b] | [b, a] | {a, b} => a }";
+        assert!(Parser::new(Lexer::new(src.into())).parse_program().is_ok());
+    }
+
+    #[test]
+    fn pipeline_desugars_to_call() {
+        let piped = Parser::new(Lexer::new("x |> f(y) |> g;".into())).parse_program();
+        let called = Parser::new(Lexer::new("g(f(x, y));".into())).parse_program();
+        assert_eq!(piped, called);
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Ident(String),
+    Integer(i32),
+    String(String),
+    Boolean(bool),
+    Alternative(Vec<Pattern>),
+    Array(Vec<Pattern>, Option<String>),
+    Hash(Vec<String>),
+    // The variant name, optionally qualified as `Shape.Circle`
+    Variant(String, Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Pattern::Ident(name) => vec![name.clone()],
+            Pattern::Array(elements, rest) => {
+                let mut names: Vec<String> = elements.iter().flat_map(|e| e.names()).collect();
+                names.extend(rest.clone());
+                names
+            }
+            Pattern::Hash(keys) => keys.clone(),
+            Pattern::Variant(_, values) => values.iter().flat_map(|v| v.names()).collect(),
+            Pattern::Alternative(patterns) => match patterns.first() {
+                Some(pattern) => pattern.names(),
+                None => Vec::new(),
+            },
+            Pattern::Wildcard | Pattern::Integer(_) | Pattern::String(_) | Pattern::Boolean(_) => {
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Ident(name) => write!(f, "{}", name),
+            Pattern::Integer(integer) => write!(f, "{}", integer),
+            Pattern::String(string) => write!(f, "{:?}", string),
+            Pattern::Boolean(boolean) => write!(f, "{}", boolean),
+            Pattern::Alternative(patterns) => {
+                let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}", patterns.join(" | "))
+            }
+            Pattern::Array(elements, rest) => {
+                let mut items: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                if let Some(rest) = rest {
+                    items.push(format!("...{}", rest));
+                }
+                write!(f, "[{}]", items.join(", "))
+            }
+            Pattern::Hash(keys) => write!(f, "{{{}}}", keys.join(", ")),
+            Pattern::Variant(name, values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "{}({})", name, values.join(", "))
+            }
+        }
+    }
+}
@@ -1,14 +1,6 @@
 use crate::statement::Statement;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
-
-impl Default for Program {
-    fn default() -> Self {
-        Program {
-            statements: Vec::new(),
-        }
-    }
-}
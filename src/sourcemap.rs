@@ -0,0 +1,83 @@
+use crate::lexer::Lexer;
+use crate::tokeninfo::TokenInfo;
+
+pub type FileId = usize;
+
+const UNKNOWN_PATH: &str = "<input>";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub path: String,
+    pub src: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap { files: Vec::new() }
+    }
+
+    pub fn add(&mut self, path: &str, src: String) -> FileId {
+        self.files.push(SourceFile {
+            path: path.into(),
+            src,
+        });
+        self.files.len() - 1
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<FileId, String> {
+        let src = std::fs::read_to_string(path).map_err(|err| format!("{} : {}", path, err))?;
+        Ok(self.add(path, src))
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file)
+    }
+
+    pub fn path(&self, file: FileId) -> &str {
+        match self.get(file) {
+            Some(source) => &source.path,
+            None => UNKNOWN_PATH,
+        }
+    }
+
+    pub fn lexer(&self, file: FileId) -> Lexer {
+        let src = match self.get(file) {
+            Some(source) => source.src.clone(),
+            None => String::new(),
+        };
+        Lexer::with_file(src, file)
+    }
+
+    pub fn location(&self, token_info: &TokenInfo) -> String {
+        format!(
+            "{}:{}:{}",
+            self.path(token_info.file),
+            token_info.line,
+            token_info.col
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::token::TokenType;
+
+    #[test]
+    fn tokens_reference_their_file() {
+        let mut sources = SourceMap::new();
+        let first = sources.add("first.ch", "let a = 1;".into());
+        let second = sources.add("second.ch", "\n  let b = 2;".into());
+
+        let token = sources.lexer(second).next_token();
+        assert_eq!(token.token_type, TokenType::Let);
+        assert_eq!(token.token_info.file, second);
+        assert_eq!(sources.location(&token.token_info), "second.ch:2:3");
+        assert_eq!(sources.path(first), "first.ch");
+    }
+}
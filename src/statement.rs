@@ -1,11 +1,46 @@
+use std::rc::Rc;
+
 use crate::expression::Expression;
+use crate::expression::Parameter;
+use crate::pattern::Pattern;
+use crate::sourcemap::FileId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportNames {
+    // `import "utils.ch" as utils;`
+    Module(String),
+    // `import { a, b } from "utils.ch";`
+    Names(Vec<String>),
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
-    Let(String, Box<Expression>),
+    Let(Pattern, Box<Expression>),
+    Const(Pattern, Box<Expression>),
     Return(Box<Expression>),
+    Break,
+    Continue,
     ExpressionStatement(Box<Expression>),
     BlockStatement(Vec<Statement>),
     Reassignment(String, Box<Expression>),
+    Function(String, Vec<Parameter>, Rc<Statement>),
+    Struct(String, Vec<String>),
+    Enum(String, Vec<(String, Vec<String>)>),
+    Import(String, ImportNames, FileId),
+    Export(Box<Statement>),
     Program(Vec<Statement>),
 }
+
+impl Statement {
+    // Names a declaration binds in its scope
+    pub fn declared_names(&self) -> Vec<String> {
+        match self {
+            Statement::Let(pattern, _) | Statement::Const(pattern, _) => pattern.names(),
+            Statement::Function(name, _, _)
+            | Statement::Struct(name, _)
+            | Statement::Enum(name, _) => vec![name.clone()],
+            Statement::Export(statement) => statement.declared_names(),
+            _ => Vec::new(),
+        }
+    }
+}
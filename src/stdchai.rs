@@ -1,14 +1,19 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::interpreter::error;
+use crate::module::ModuleLoader;
 use crate::object::BuildinFunction;
 use crate::object::ChaiObject;
+use crate::object::HashKey;
+use crate::sourcemap::SourceMap;
 
 const NULL: ChaiObject = ChaiObject::Null;
 
 #[derive(Clone)]
 pub struct Std {
     buildinfunctions: HashMap<String, BuildinFunction>,
+    pub modules: ModuleLoader,
 }
 
 fn len(object: Vec<ChaiObject>) -> ChaiObject {
@@ -16,9 +21,10 @@ fn len(object: Vec<ChaiObject>) -> ChaiObject {
         return error(&format!("Expected 1 argument, found {}", object.len()));
     }
     match &object[0] {
-        ChaiObject::String(string) => ChaiObject::Integer(string.len() as i32),
+        ChaiObject::String(string) => ChaiObject::Integer(string.chars().count() as i32),
         ChaiObject::Array(array) => ChaiObject::Integer(array.len() as i32),
-        _ => error(&format!("Expected String, found {}", &object[0])),
+        ChaiObject::Hash(hash) => ChaiObject::Integer(hash.len() as i32),
+        _ => error(&format!("Expected String, found {}", object[0].type_name())),
     }
 }
 
@@ -31,7 +37,7 @@ fn push(object: Vec<ChaiObject>) -> ChaiObject {
         if let ChaiObject::Error(_) = object[1] {
             return object[1].clone();
         }
-        array.push(object[1].clone());
+        Rc::make_mut(&mut array).push(object[1].clone());
         return ChaiObject::Array(array);
     }
 
@@ -44,37 +50,129 @@ fn pop(object: Vec<ChaiObject>) -> ChaiObject {
     }
 
     if let ChaiObject::Array(mut array) = object[0].clone() {
-        array.pop();
+        Rc::make_mut(&mut array).pop();
         return ChaiObject::Array(array);
     }
 
     error("First argument should be an array")
 }
 
-fn serialize_chai_object(object: ChaiObject) -> String {
-    match object {
-        ChaiObject::Integer(integer) => integer.to_string(),
-        ChaiObject::Boolean(boolean) => boolean.to_string(),
-        ChaiObject::String(string) => string,
-        ChaiObject::Array(array) => {
-            let mut array_items: Vec<String> = Vec::new();
-            for item in array {
-                array_items.push(serialize_chai_object(item));
+fn range(object: Vec<ChaiObject>) -> ChaiObject {
+    match object.as_slice() {
+        [ChaiObject::Integer(end)] => ChaiObject::Range(Some(0), Some(*end)),
+        [ChaiObject::Integer(start), ChaiObject::Integer(end)] => {
+            ChaiObject::Range(Some(*start), Some(*end))
+        }
+        [_] | [_, _] => error("Expected Integer arguments"),
+        _ => error(&format!(
+            "Expected 1 or 2 arguments, found {}",
+            object.len()
+        )),
+    }
+}
+
+fn keys(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 1 {
+        return error(&format!("Expected 1 argument, found {}", object.len()));
+    }
+
+    if let ChaiObject::Hash(hash) = &object[0] {
+        return ChaiObject::Array(Rc::new(
+            hash.keys().cloned().map(ChaiObject::from).collect(),
+        ));
+    }
+
+    error("First argument should be a hash")
+}
+
+fn values(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 1 {
+        return error(&format!("Expected 1 argument, found {}", object.len()));
+    }
+
+    if let ChaiObject::Hash(hash) = &object[0] {
+        return ChaiObject::Array(Rc::new(hash.values().cloned().collect()));
+    }
+
+    error("First argument should be a hash")
+}
+
+fn has_key(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 2 {
+        return error(&format!("Expected 2 argument, found {}", object.len()));
+    }
+
+    if let ChaiObject::Hash(hash) = &object[0] {
+        return match HashKey::from_object(&object[1]) {
+            Some(key) => ChaiObject::Boolean(hash.contains_key(&key)),
+            None => error(&format!("Unusable as hash key : {}", object[1].type_name())),
+        };
+    }
+
+    error("First argument should be a hash")
+}
+
+fn delete(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 2 {
+        return error(&format!("Expected 2 argument, found {}", object.len()));
+    }
+
+    if let ChaiObject::Hash(mut hash) = object[0].clone() {
+        return match HashKey::from_object(&object[1]) {
+            Some(key) => {
+                Rc::make_mut(&mut hash).remove(&key);
+                ChaiObject::Hash(hash)
             }
-            "[".to_string() + &array_items.join(",") + &"]".to_string()
+            None => error(&format!("Unusable as hash key : {}", object[1].type_name())),
+        };
+    }
+
+    error("First argument should be a hash")
+}
+
+fn upper(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 1 {
+        return error(&format!("Expected 1 argument, found {}", object.len()));
+    }
+
+    match &object[0] {
+        ChaiObject::String(string) => ChaiObject::String(string.to_uppercase()),
+        _ => error(&format!("Expected String, found {}", object[0].type_name())),
+    }
+}
+
+fn lower(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 1 {
+        return error(&format!("Expected 1 argument, found {}", object.len()));
+    }
+
+    match &object[0] {
+        ChaiObject::String(string) => ChaiObject::String(string.to_lowercase()),
+        _ => error(&format!("Expected String, found {}", object[0].type_name())),
+    }
+}
+
+fn type_of(object: Vec<ChaiObject>) -> ChaiObject {
+    if object.len() != 1 {
+        return error(&format!("Expected 1 argument, found {}", object.len()));
+    }
+
+    match &object[0] {
+        ChaiObject::Function(_) | ChaiObject::BuildinFunction(_) => {
+            ChaiObject::String("Function".into())
         }
-        _ => format!("{}", object),
+        object => ChaiObject::String(object.type_name()),
     }
 }
 
 fn print(object: Vec<ChaiObject>) -> ChaiObject {
-    if object.len() == 0 {
+    if object.is_empty() {
         return NULL;
     }
 
     let mut print_string: Vec<String> = Vec::new();
     for obj in object {
-        print_string.push(serialize_chai_object(obj));
+        print_string.push(obj.to_string());
     }
 
     ChaiObject::Print(print_string.join(" "))
@@ -82,15 +180,31 @@ fn print(object: Vec<ChaiObject>) -> ChaiObject {
 
 impl Std {
     pub fn load() -> Self {
+        Std::with_sources(SourceMap::new())
+    }
+
+    // Sources already in the map are used to resolve imports made by them
+    pub fn with_sources(sources: SourceMap) -> Self {
         let mut buildinfunctions: HashMap<String, BuildinFunction> = HashMap::new();
         buildinfunctions.insert("len".into(), len);
         buildinfunctions.insert("push".into(), push);
         buildinfunctions.insert("print".into(), print);
         buildinfunctions.insert("pop".into(), pop);
-        Std { buildinfunctions }
+        buildinfunctions.insert("range".into(), range);
+        buildinfunctions.insert("keys".into(), keys);
+        buildinfunctions.insert("values".into(), values);
+        buildinfunctions.insert("has_key".into(), has_key);
+        buildinfunctions.insert("delete".into(), delete);
+        buildinfunctions.insert("upper".into(), upper);
+        buildinfunctions.insert("lower".into(), lower);
+        buildinfunctions.insert("type_of".into(), type_of);
+        Std {
+            buildinfunctions,
+            modules: ModuleLoader::new(sources),
+        }
     }
 
-    pub fn get(self, name: &str) -> Option<BuildinFunction> {
-        self.buildinfunctions.get(name.into()).cloned()
+    pub fn get(&self, name: &str) -> Option<BuildinFunction> {
+        self.buildinfunctions.get(name).cloned()
     }
 }
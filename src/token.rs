@@ -6,12 +6,22 @@ pub enum TokenType {
     // Keywords
     Function,
     While,
+    Match,
+    For,
+    In,
     Let,
+    Const,
     True,
     False,
     If,
     Else,
     Return,
+    Break,
+    Continue,
+    Struct,
+    Enum,
+    Import,
+    Export,
     // Variables
     Ident,
     Int,
@@ -23,12 +33,26 @@ pub enum TokenType {
     Bang,
     Asterisk,
     Slash,
+    Percent,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
+    PercentAssign,
     Lessthan,
     Greaterthan,
     Equal,
     Notequal,
+    Pipeline,
+    FatArrow,
+    Bar,
     // Separtors
     Comma,
+    Colon,
+    Dot,
+    DotDot,
+    DotDotEqual,
+    Ellipsis,
     Semicolon,
     // Grouping
     Lparen,
@@ -46,17 +70,28 @@ pub enum TokenType {
 impl TokenType {
     pub fn precedence(token_type: &TokenType) -> Precedence {
         match token_type {
+            TokenType::Pipeline => Precedence::Pipeline,
             TokenType::Equal => Precedence::Equals,
             TokenType::Notequal => Precedence::Equals,
             TokenType::Lessthan => Precedence::Lessgreater,
             TokenType::Greaterthan => Precedence::Lessgreater,
+            TokenType::DotDot => Precedence::Range,
+            TokenType::DotDotEqual => Precedence::Range,
             TokenType::Plus => Precedence::Sum,
             TokenType::Minus => Precedence::Sum,
             TokenType::Asterisk => Precedence::Product,
             TokenType::Slash => Precedence::Product,
+            TokenType::Percent => Precedence::Product,
             TokenType::Lparen => Precedence::Call,
+            TokenType::Dot => Precedence::Call,
             TokenType::Lbracket => Precedence::Index,
-            TokenType::Assign => Precedence::Assign,
+            TokenType::Lbrace => Precedence::Call,
+            TokenType::Assign
+            | TokenType::PlusAssign
+            | TokenType::MinusAssign
+            | TokenType::AsteriskAssign
+            | TokenType::SlashAssign
+            | TokenType::PercentAssign => Precedence::Assign,
             _ => Precedence::Lowest,
         }
     }
@@ -1,6 +1,9 @@
+use crate::sourcemap::FileId;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct TokenInfo {
     pub litertal: String,
+    pub file: FileId,
     pub line: usize,
     pub col: usize,
 }
@@ -9,6 +12,7 @@ impl Default for TokenInfo {
     fn default() -> Self {
         TokenInfo {
             litertal: "".into(),
+            file: 0,
             line: 0,
             col: 0,
         }